@@ -0,0 +1,289 @@
+// analysis.rs
+
+use crate::errors::AudioError;
+use std::time::Duration;
+
+/// Overall signal levels measured by FFmpeg's astats filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelStats {
+    /// Highest absolute sample level in dBFS (`-inf` for digital silence).
+    pub peak_db: f32,
+    /// RMS level in dBFS (`-inf` for digital silence).
+    pub rms_db: f32,
+    /// Mean sample value across all channels, in the range -1.0..1.0.
+    pub dc_offset: f32,
+}
+
+/// Summary of the silent stretches in a recording, from `AudioProcessor::silence_report`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SilenceReport {
+    /// Combined length of all silent regions.
+    pub total_silence: Duration,
+    /// `(start, end)` of each silent region, in order.
+    pub regions: Vec<(Duration, Duration)>,
+    /// Fraction of the recording that is silent, 0.0 to 1.0.
+    pub silence_ratio: f32,
+}
+
+/// Returns the value of a `key: value` line printed by an FFmpeg filter, ignoring the
+/// `[Parsed_x @ 0x..]` prefix.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let (_, rest) = line.split_once(key)?;
+    rest.strip_prefix(':').map(str::trim)
+}
+
+/// Parses the "Overall" section of astats output from ffmpeg's stderr.
+pub(crate) fn parse_astats(stderr: &str) -> Result<LevelStats, AudioError> {
+    let overall = stderr
+        .rfind("Overall")
+        .map(|index| &stderr[index..])
+        .ok_or_else(|| AudioError::FfmpegError("astats produced no overall statistics".to_string()))?;
+    let mut peak_db = None;
+    let mut rms_db = None;
+    let mut dc_offset = None;
+    for line in overall.lines() {
+        if let Some(value) = field(line, "Peak level dB") {
+            peak_db = value.parse().ok();
+        } else if let Some(value) = field(line, "RMS level dB") {
+            rms_db = value.parse().ok();
+        } else if let Some(value) = field(line, "DC offset") {
+            dc_offset = value.parse().ok();
+        }
+    }
+    match (peak_db, rms_db, dc_offset) {
+        (Some(peak_db), Some(rms_db), Some(dc_offset)) => Ok(LevelStats { peak_db, rms_db, dc_offset }),
+        _ => Err(AudioError::FfmpegError("could not parse astats output".to_string())),
+    }
+}
+
+/// Parses the integrated loudness (`I: -23.0 LUFS`) from the ebur128 summary.
+pub(crate) fn parse_integrated_loudness(stderr: &str) -> Result<f32, AudioError> {
+    let summary = stderr
+        .rfind("Summary:")
+        .map(|index| &stderr[index..])
+        .ok_or_else(|| AudioError::FfmpegError("ebur128 produced no summary".to_string()))?;
+    summary
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("I:"))
+        .filter_map(|value| value.trim().trim_end_matches("LUFS").trim().parse().ok())
+        .next()
+        .ok_or_else(|| AudioError::FfmpegError("could not parse integrated loudness".to_string()))
+}
+
+/// Parses the `silence_start`/`silence_end` lines silencedetect prints into `(start, end)`
+/// pairs. A silence still open when the input ends is closed at `total`. Decimal commas are
+/// accepted in case ffmpeg was built to format numbers for the locale.
+pub(crate) fn parse_silence_regions(stderr: &str, total: Duration) -> Result<Vec<(Duration, Duration)>, AudioError> {
+    let seconds = |value: &str| -> Result<Duration, AudioError> {
+        let value = value.split_whitespace().next().unwrap_or_default().replace(',', ".");
+        value
+            .parse::<f64>()
+            .map(|secs| Duration::from_secs_f64(secs.max(0.0)))
+            .map_err(|_| AudioError::FfmpegError(format!("could not parse silencedetect time '{}'", value)))
+    };
+    let mut regions = Vec::new();
+    let mut open = None;
+    for line in stderr.lines() {
+        if let Some(value) = field(line, "silence_start") {
+            open = Some(seconds(value)?);
+        } else if let Some(value) = field(line, "silence_end") {
+            if let Some(start) = open.take() {
+                regions.push((start, seconds(value)?));
+            }
+        }
+    }
+    if let Some(start) = open {
+        regions.push((start, total.max(start)));
+    }
+    Ok(regions)
+}
+
+/// Parses the true peak (`Peak: -1.0 dBFS` under `True peak:`) from an ebur128 summary
+/// produced with `peak=true`.
+pub(crate) fn parse_true_peak(stderr: &str) -> Result<f32, AudioError> {
+    let section = stderr
+        .rfind("True peak:")
+        .map(|index| &stderr[index..])
+        .ok_or_else(|| AudioError::FfmpegError("ebur128 produced no true peak".to_string()))?;
+    section
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Peak:"))
+        .filter_map(|value| value.trim().trim_end_matches("dBFS").trim().parse().ok())
+        .next()
+        .ok_or_else(|| AudioError::FfmpegError("could not parse true peak".to_string()))
+}
+
+/// Returns the index nearest `center` where `samples` crosses zero, searching up to
+/// `max_distance` samples either way. At a sign change the sample closer to zero is
+/// returned; `None` if the window holds no crossing.
+pub(crate) fn nearest_zero_crossing(samples: &[f32], center: usize, max_distance: usize) -> Option<usize> {
+    let crossing_at = |index: usize| -> Option<usize> {
+        let (&before, &after) = (samples.get(index.checked_sub(1)?)?, samples.get(index)?);
+        if before == 0.0 || before.signum() != after.signum() {
+            Some(if before.abs() < after.abs() { index - 1 } else { index })
+        } else {
+            None
+        }
+    };
+    (0..=max_distance).find_map(|distance| {
+        crossing_at(center + distance).or_else(|| center.checked_sub(distance).and_then(crossing_at))
+    })
+}
+
+/// Returns the RMS of the difference between the first two channels of interleaved
+/// `samples`, relative to the RMS of the louder of the two (0.0 for identical channels).
+/// Silence returns 0.0.
+pub(crate) fn channel_difference(samples: &[f32], channels: usize) -> f32 {
+    if channels < 2 {
+        return 0.0;
+    }
+    let (mut left, mut right, mut side) = (0.0f64, 0.0f64, 0.0f64);
+    for frame in samples.chunks_exact(channels) {
+        let (l, r) = (f64::from(frame[0]), f64::from(frame[1]));
+        left += l * l;
+        right += r * r;
+        side += (l - r) * (l - r);
+    }
+    let louder = left.max(right);
+    if louder == 0.0 { 0.0 } else { (side / louder).sqrt() as f32 }
+}
+
+/// Returns the zero-lag cross-correlation of the first two channels of interleaved
+/// `samples`, normalized to -1.0..1.0: 1.0 for identical channels, -1.0 when one is the
+/// other with its polarity inverted. Silence in either channel returns 0.0.
+pub(crate) fn channel_correlation(samples: &[f32], channels: usize) -> f32 {
+    if channels < 2 {
+        return 0.0;
+    }
+    let (mut left, mut right, mut cross) = (0.0f64, 0.0f64, 0.0f64);
+    for frame in samples.chunks_exact(channels) {
+        let (l, r) = (f64::from(frame[0]), f64::from(frame[1]));
+        left += l * l;
+        right += r * r;
+        cross += l * r;
+    }
+    if left == 0.0 || right == 0.0 { 0.0 } else { (cross / (left * right).sqrt()) as f32 }
+}
+
+/// Approximates the TT DR meter's dynamic range, in dB, for interleaved samples.
+/// Each channel is cut into 3-second blocks; its DR is the second-highest block peak over
+/// the RMS of the loudest 20% of blocks (using the meter's sqrt(2)-scaled RMS, so a pure
+/// sine reads 0 dB). The result is the mean across channels.
+pub(crate) fn dynamic_range_db(samples: &[f32], channels: usize, sample_rate: u32) -> f32 {
+    if channels == 0 || samples.is_empty() {
+        return 0.0;
+    }
+    let block_len = (sample_rate as usize * 3).max(1) * channels;
+    let total: f32 = (0..channels)
+        .map(|channel| {
+            let mut peaks = Vec::new();
+            let mut rms = Vec::new();
+            for block in samples.chunks(block_len) {
+                let values: Vec<f32> = block.iter().skip(channel).step_by(channels).copied().collect();
+                if values.is_empty() {
+                    continue;
+                }
+                let mean_square = values.iter().map(|v| v * v).sum::<f32>() / values.len() as f32;
+                rms.push((2.0 * mean_square).sqrt());
+                peaks.push(values.iter().fold(0.0f32, |peak, v| peak.max(v.abs())));
+            }
+            rms.sort_by(|a, b| b.total_cmp(a));
+            peaks.sort_by(|a, b| b.total_cmp(a));
+            let loudest = ((rms.len() as f32 * 0.2).round() as usize).clamp(1, rms.len());
+            let top_rms = (rms[..loudest].iter().map(|r| r * r).sum::<f32>() / loudest as f32).sqrt();
+            let peak = peaks.get(1).or(peaks.first()).copied().unwrap_or(0.0);
+            if top_rms > 0.0 && peak > 0.0 { 20.0 * (peak / top_rms).log10() } else { 0.0 }
+        })
+        .sum();
+    total / channels as f32
+}
+
+/// Returns the largest drop in level, in dB, from `input` to `output` (mono samples at
+/// the same rate), compared in `window`-sample RMS blocks with `makeup_db` taken out of
+/// the output. Blocks quieter than -60 dBFS at the input are ignored, as are reductions
+/// below zero.
+pub(crate) fn max_gain_reduction_db(input: &[f32], output: &[f32], window: usize, makeup_db: f32) -> f32 {
+    let rms_db = |block: &[f32]| {
+        let mean_square = block.iter().map(|v| v * v).sum::<f32>() / block.len() as f32;
+        10.0 * mean_square.log10()
+    };
+    input
+        .chunks(window.max(1))
+        .zip(output.chunks(window.max(1)))
+        .filter_map(|(before, after)| {
+            let before_db = rms_db(before);
+            (before_db > -60.0).then(|| before_db - (rms_db(after) - makeup_db))
+        })
+        .filter(|reduction| reduction.is_finite())
+        .fold(0.0, f32::max)
+}
+
+/// Returns the lag, in samples, at which `other` best matches `reference` by
+/// cross-correlation: positive when events in `other` happen later than in `reference`.
+/// The correlation is computed via FFT, so memory grows with the combined length.
+pub(crate) fn correlation_lag(reference: &[f32], other: &[f32]) -> isize {
+    let size = (reference.len() + other.len()).max(2).next_power_of_two();
+    let mut a = to_complex(reference, size);
+    let mut b = to_complex(other, size);
+    fft(&mut a, false);
+    fft(&mut b, false);
+    // corr[k] = sum(reference[n] * other[n + k]) = ifft(conj(A) * B)
+    let mut product: Vec<(f64, f64)> = a
+        .iter()
+        .zip(&b)
+        .map(|(&(ar, ai), &(br, bi))| (ar * br + ai * bi, ar * bi - ai * br))
+        .collect();
+    fft(&mut product, true);
+    let (best, _) = product
+        .iter()
+        .enumerate()
+        .fold((0, f64::NEG_INFINITY), |best, (index, &(re, _))| if re > best.1 { (index, re) } else { best });
+    // Indices past the midpoint are negative lags that wrapped around.
+    if best > size / 2 { best as isize - size as isize } else { best as isize }
+}
+
+fn to_complex(samples: &[f32], size: usize) -> Vec<(f64, f64)> {
+    let mut values: Vec<(f64, f64)> = samples.iter().map(|&s| (f64::from(s), 0.0)).collect();
+    values.resize(size, (0.0, 0.0));
+    values
+}
+
+/// In-place iterative radix-2 FFT; `values.len()` must be a power of two.
+/// The inverse transform is scaled by `1 / len`.
+fn fft(values: &mut [(f64, f64)], inverse: bool) {
+    let len = values.len();
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut width = 2;
+    while width <= len {
+        let angle = sign * 2.0 * std::f64::consts::PI / width as f64;
+        for start in (0..len).step_by(width) {
+            for k in 0..width / 2 {
+                let (wr, wi) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (ur, ui) = values[start + k];
+                let (xr, xi) = values[start + k + width / 2];
+                let (vr, vi) = (xr * wr - xi * wi, xr * wi + xi * wr);
+                values[start + k] = (ur + vr, ui + vi);
+                values[start + k + width / 2] = (ur - vr, ui - vi);
+            }
+        }
+        width <<= 1;
+    }
+    if inverse {
+        for value in values.iter_mut() {
+            value.0 /= len as f64;
+            value.1 /= len as f64;
+        }
+    }
+}
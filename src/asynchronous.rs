@@ -0,0 +1,92 @@
+// asynchronous.rs
+
+use crate::errors::AudioError;
+use crate::processing::AudioEffect;
+use crate::transcoding::{AudioFormat, TranscodeOptions};
+use crate::{ffmpeg, stream_copy_args, trim_input_opts, AudioProcessor};
+use std::time::Duration;
+
+/// Async versions of the core operations, available with the `async` feature.
+/// Each builds the same ffmpeg arguments as its blocking counterpart and writes the same
+/// output, but awaits ffmpeg on tokio instead of blocking the thread. They must be polled
+/// inside a tokio runtime. Steps that only probe the input (the encoder check, and the
+/// duration or sample rate some effects depend on) still run ffprobe synchronously.
+impl AudioProcessor {
+    /// Async version of `seek`.
+    pub async fn seek_async(&self, position: Duration) -> Result<Self, AudioError> {
+        self.seek_to_async(position, &self.prefixed_output("seeked")).await
+    }
+
+    /// Async version of `seek_to`.
+    pub async fn seek_to_async(&self, position: Duration, output_path: &str) -> Result<Self, AudioError> {
+        let output_file = output_path.to_string();
+        let pos_str = format!("{}", position.as_secs_f64());
+        self.run_ffmpeg_async(&["-ss", &pos_str], &stream_copy_args(&output_file), "seek").await?;
+        println!("Seeked {} seconds into {} -> {}", pos_str, self.file_path, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Async version of `trim`.
+    pub async fn trim_async(&self, start: Duration, end: Duration) -> Result<Self, AudioError> {
+        self.trim_to_async(start, end, &self.prefixed_output("trimmed")).await
+    }
+
+    /// Async version of `trim_to`.
+    pub async fn trim_to_async(&self, start: Duration, end: Duration, output_path: &str) -> Result<Self, AudioError> {
+        let (start, end) = self.trim_bounds(start, end)?;
+        let output_file = output_path.to_string();
+        let (start_str, end_str) = (format!("{}", start.as_secs_f64()), format!("{}", end.as_secs_f64()));
+        self.run_ffmpeg_async(&trim_input_opts(&start_str, &end_str), &stream_copy_args(&output_file), "trim").await?;
+        println!("Trimmed {} from {} to {} seconds -> {}", self.file_path, start_str, end_str, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Async version of `transcode`.
+    pub async fn transcode_async(&self, output_format: AudioFormat, output_path: &str) -> Result<(), AudioError> {
+        let output_path = output_format.output_path(output_path)?;
+        ffmpeg::require_encoder(&self.config, output_format.default_codec())?;
+        println!("Transcoding {} to format {:?}", self.file_path, output_format);
+        self.transcode_with_async(&output_path, TranscodeOptions::default()).await
+    }
+
+    /// Async version of `transcode_with`.
+    pub async fn transcode_with_async(&self, output_path: &str, options: TranscodeOptions) -> Result<(), AudioError> {
+        options.check_codec(output_path)?;
+        if let Some(codec) = &options.codec {
+            ffmpeg::require_encoder(&self.config, codec.as_ffmpeg_arg())?;
+        }
+        let options_args = options.to_args();
+        let mut args: Vec<&str> = options_args.iter().map(String::as_str).collect();
+        args.push(output_path);
+        self.run_ffmpeg_async(&[], &args, "transcode").await?;
+        println!("Transcoded {} with {:?} -> {}", self.file_path, options, output_path);
+        Ok(())
+    }
+
+    /// Async version of `transcode_with_options`.
+    #[deprecated(note = "use `transcode_with_async`, which takes the output path first")]
+    pub async fn transcode_with_options_async(&self, opts: TranscodeOptions, output_path: &str) -> Result<(), AudioError> {
+        self.transcode_with_async(output_path, opts).await
+    }
+
+    /// Async version of `apply_effect`.
+    pub async fn apply_effect_async(&self, effect: AudioEffect) -> Result<Self, AudioError> {
+        self.apply_effect_to_async(effect, &self.prefixed_output("effected")).await
+    }
+
+    /// Async version of `apply_effect_to`.
+    pub async fn apply_effect_to_async(&self, effect: AudioEffect, output_path: &str) -> Result<Self, AudioError> {
+        let args = self.effect_args(&effect, output_path)?;
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.run_ffmpeg_async(&[], &args, "apply effect").await?;
+        println!("Applied effect {:?} on {} -> {}", effect, self.file_path, output_path);
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Like `run_ffmpeg`, but awaits ffmpeg on tokio.
+    async fn run_ffmpeg_async(&self, input_opts: &[&str], output_args: &[&str], operation: &str) -> Result<(), AudioError> {
+        let args = self.ffmpeg_args(input_opts, output_args);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        ffmpeg::run_async(&self.config, &args, self.pipe_input.as_ref(), operation).await
+    }
+}
@@ -0,0 +1,194 @@
+// config.rs
+
+use crate::errors::AudioError;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// What `adjust_volume` does when the requested gain would push the peak above 0 dBFS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClippingPolicy {
+    /// Apply the gain as requested and let the output clip.
+    Allow,
+    /// Apply the gain followed by a limiter that holds the peak just under 0 dBFS.
+    Limit,
+    /// Fail with `InvalidParameter` instead of writing a clipped file.
+    Reject,
+}
+
+/// What `merge_audios` does when its inputs have different channel counts, which the
+/// stream-copy concat would otherwise join into a corrupt file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMismatchPolicy {
+    /// Fail with `InvalidParameter`.
+    Error,
+    /// Re-encode mismatched inputs to the first input's channel count.
+    ConformToFirst,
+    /// Re-encode mismatched inputs to the highest channel count among the inputs.
+    ConformToMax,
+}
+
+/// Settings shared by every operation of an `AudioProcessor`.
+/// Processors returned by an operation inherit the config of the processor they came from.
+#[derive(Debug, Clone)]
+pub struct AudioProcessorConfig {
+    /// Slowest speed factor `change_speed` will render.
+    pub min_speed: f32,
+    /// Fastest speed factor `change_speed` will render.
+    pub max_speed: f32,
+    /// When true, out-of-range speed factors are clamped into `[min_speed, max_speed]`
+    /// with a warning; when false they are rejected with `InvalidParameter`.
+    pub clamp_speed: bool,
+    /// When true, outputs are written to a temp file in the destination directory and
+    /// renamed into place on success, so an interrupted run never leaves a partial file
+    /// at the final path. Runs that write numbered segments (`split_into_segments`,
+    /// `to_hls`) or fan out through the tee muxer write in place instead.
+    pub atomic_output: bool,
+    /// When true, video and subtitle streams are dropped from outputs (`-vn -sn`), so
+    /// audio operations on video containers neither carry nor trip over other streams.
+    pub audio_only: bool,
+    /// When true, single-input operations copy the input's tags (title, artist, ...) to
+    /// their output (`-map_metadata 0`); when false, outputs are written without them.
+    /// Operations that set their own metadata mapping, like `transcode_with` with
+    /// `TranscodeOptions::preserve_metadata`, win.
+    pub preserve_metadata: bool,
+    /// Environment variables set on every spawned ffmpeg/ffprobe process,
+    /// e.g. `FFREPORT` to have FFmpeg write a log file.
+    pub env: HashMap<String, String>,
+    /// How `adjust_volume` handles gains that would clip. Any policy other than `Allow`
+    /// measures the input's peak first, which costs an extra decode pass for boosts.
+    pub clipping_policy: ClippingPolicy,
+    /// When false, an output file that ffmpeg created or overwrote is deleted if the run
+    /// fails, so a truncated file is never mistaken for a finished one. Set to true to
+    /// keep it for debugging.
+    pub keep_partial_output: bool,
+    /// How `merge_audios` handles inputs whose channel counts differ. The merge uses the
+    /// first input's config.
+    pub merge_mismatch_policy: MergeMismatchPolicy,
+    /// Directory ffmpeg/ffprobe run in, and that relative input and output paths are
+    /// resolved against. `None` uses the current directory of the process.
+    pub working_dir: Option<PathBuf>,
+    /// ffmpeg binary to run. When unset, the `AUDIO_PROCESSOR_FFMPEG` environment variable
+    /// is used if present, otherwise `ffmpeg` is looked up on `PATH`.
+    pub ffmpeg_path: Option<PathBuf>,
+    /// ffprobe binary to run. When unset, `AUDIO_PROCESSOR_FFPROBE` is used if present,
+    /// then an `ffprobe` next to the configured ffmpeg, otherwise `ffprobe` on `PATH`.
+    pub ffprobe_path: Option<PathBuf>,
+    /// Bytes ffmpeg/ffprobe read to detect the input's streams (`-probesize`). Raise it
+    /// for inputs that fail with "could not find codec parameters".
+    pub probesize: Option<u64>,
+    /// How much of the input is analyzed to detect its streams (`-analyzeduration`).
+    pub analyze_duration: Option<Duration>,
+}
+
+impl Default for AudioProcessorConfig {
+    fn default() -> Self {
+        AudioProcessorConfig {
+            min_speed: 0.1,
+            max_speed: 10.0,
+            clamp_speed: true,
+            atomic_output: false,
+            audio_only: true,
+            preserve_metadata: true,
+            env: HashMap::new(),
+            clipping_policy: ClippingPolicy::Limit,
+            keep_partial_output: false,
+            merge_mismatch_policy: MergeMismatchPolicy::Error,
+            working_dir: None,
+            ffmpeg_path: None,
+            ffprobe_path: None,
+            probesize: None,
+            analyze_duration: None,
+        }
+    }
+}
+
+impl AudioProcessorConfig {
+    /// Returns the ffmpeg binary to spawn; see `ffmpeg_path`.
+    pub fn ffmpeg_binary(&self) -> PathBuf {
+        self.ffmpeg_path
+            .clone()
+            .or_else(|| std::env::var_os("AUDIO_PROCESSOR_FFMPEG").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("ffmpeg"))
+    }
+
+    /// Returns the ffprobe binary to spawn; see `ffprobe_path`.
+    pub fn ffprobe_binary(&self) -> PathBuf {
+        if let Some(path) = self.ffprobe_path.clone().or_else(|| std::env::var_os("AUDIO_PROCESSOR_FFPROBE").map(PathBuf::from)) {
+            return path;
+        }
+        let ffmpeg = self.ffmpeg_binary();
+        match ffmpeg.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => {
+                let sibling = dir.join("ffprobe").with_extension(ffmpeg.extension().unwrap_or_default());
+                if sibling.exists() { sibling } else { PathBuf::from("ffprobe") }
+            }
+            _ => PathBuf::from("ffprobe"),
+        }
+    }
+
+    /// Returns the stream detection options placed before the input's `-i`.
+    pub(crate) fn input_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(bytes) = self.probesize {
+            options.extend(["-probesize".to_string(), bytes.to_string()]);
+        }
+        if let Some(duration) = self.analyze_duration {
+            options.extend(["-analyzeduration".to_string(), duration.as_micros().to_string()]);
+        }
+        options
+    }
+
+    /// Resolves `path` the way spawned processes will see it: relative paths are joined
+    /// onto `working_dir` when one is set.
+    pub(crate) fn resolve_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        match &self.working_dir {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Checks `path` with `io::input_is_accessible`. URLs and lavfi sources are classified
+    /// on the string as given, and only plain paths are resolved against `working_dir`.
+    pub(crate) fn input_is_accessible(&self, path: &str) -> bool {
+        crate::io::is_url(path)
+            || crate::io::is_lavfi_source(path)
+            || crate::io::input_is_accessible(&self.resolve_path(path).to_string_lossy())
+    }
+
+    /// Returns the speed factor that will actually be rendered for a requested factor.
+    /// Quality degrades noticeably near either end of the range, so the limits are
+    /// a safety net rather than a recommendation. Zero, negative and non-finite factors
+    /// have no meaningful tempo and are always rejected, even when clamping. A config whose
+    /// `min_speed`/`max_speed` don't form a positive, finite range fails with `InvalidParameter`.
+    pub fn speed_factor(&self, factor: f32) -> Result<f32, AudioError> {
+        let (min, max) = (self.min_speed, self.max_speed);
+        if !(min.is_finite() && max.is_finite() && 0.0 < min && min <= max) {
+            return Err(AudioError::InvalidParameter(format!(
+                "speed range [{}, {}] must be positive, finite and in order", min, max
+            )));
+        }
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(AudioError::InvalidParameter(format!(
+                "speed factor must be a positive finite number, got {}", factor
+            )));
+        }
+        if factor >= self.min_speed && factor <= self.max_speed {
+            return Ok(factor);
+        }
+        if self.clamp_speed {
+            let clamped = factor.clamp(self.min_speed, self.max_speed);
+            log::warn!(
+                "speed factor {} is outside [{}, {}]; clamping to {}",
+                factor, self.min_speed, self.max_speed, clamped
+            );
+            Ok(clamped)
+        } else {
+            Err(AudioError::InvalidParameter(format!(
+                "speed factor {} is outside the supported range [{}, {}]",
+                factor, self.min_speed, self.max_speed
+            )))
+        }
+    }
+}
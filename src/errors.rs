@@ -4,7 +4,19 @@
 #[derive(Debug)]
 pub enum AudioError {
     IoError(std::io::Error),
+    /// FFmpeg produced output this crate couldn't interpret.
     FfmpegError(String),
+    /// An ffmpeg or ffprobe process exited unsuccessfully. `stderr` holds the end of its
+    /// diagnostic output (at most the last 8 KB); `exit_code` is `None` if it was killed
+    /// by a signal.
+    FfmpegFailed { operation: String, stderr: String, exit_code: Option<i32> },
     InvalidParameter(String),
+    /// The ffmpeg or ffprobe executable couldn't be found; the message names the binary tried.
+    FfmpegNotFound(String),
+    /// The named operation was stopped through its handle before it finished; any output
+    /// it had written was removed.
+    Cancelled(String),
+    /// The ffmpeg build lacks an encoder the operation needs; the message names it.
+    UnsupportedFormat(String),
     // Other error types as needed
 }
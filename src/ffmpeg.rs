@@ -0,0 +1,899 @@
+// ffmpeg.rs
+
+use crate::config::AudioProcessorConfig;
+use crate::errors::AudioError;
+use std::fmt;
+use std::io::{BufRead, BufReader, ErrorKind, Lines, Read};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Builds a command for `program` (`ffmpeg` or `ffprobe`) with the config's process
+/// settings applied, including the binary path configured for it.
+fn command(config: &AudioProcessorConfig, program: &str) -> Command {
+    let binary = match program {
+        "ffprobe" => config.ffprobe_binary(),
+        _ => config.ffmpeg_binary(),
+    };
+    let mut command = Command::new(binary);
+    command.envs(&config.env);
+    if let Some(dir) = &config.working_dir {
+        command.current_dir(dir);
+    }
+    command
+}
+
+/// Converts a failure to start `program` into an error, reporting a missing binary as
+/// `FfmpegNotFound` rather than a bare `NotFound` I/O error.
+fn spawn_error(config: &AudioProcessorConfig, program: &str, e: std::io::Error) -> AudioError {
+    // A missing working directory also surfaces as NotFound.
+    let bad_working_dir = config.working_dir.as_ref().is_some_and(|dir| !dir.is_dir());
+    if e.kind() != ErrorKind::NotFound || bad_working_dir {
+        return AudioError::IoError(e);
+    }
+    let binary = if program == "ffprobe" { config.ffprobe_binary() } else { config.ffmpeg_binary() };
+    AudioError::FfmpegNotFound(format!(
+        "{} executable not found (tried {}); install it or set the binary path",
+        program,
+        binary.display()
+    ))
+}
+
+/// Checks once per binary that ffmpeg can be started, so a missing install is reported
+/// when a processor is created rather than on its first operation.
+pub(crate) fn ensure_available(config: &AudioProcessorConfig) -> Result<(), AudioError> {
+    static AVAILABLE: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    let binary = config.ffmpeg_binary();
+    let available = AVAILABLE.get_or_init(|| Mutex::new(HashSet::new()));
+    if available.lock().map(|set| set.contains(&binary)).unwrap_or(false) {
+        return Ok(());
+    }
+    command(config, "ffmpeg")
+        .arg("-version")
+        .output()
+        .map_err(|e| spawn_error(config, "ffmpeg", e))?;
+    if let Ok(mut set) = available.lock() {
+        set.insert(binary);
+    }
+    Ok(())
+}
+
+/// Returns the encoders the configured ffmpeg was built with, from `ffmpeg -encoders`.
+/// The list is read once per binary and cached.
+pub(crate) fn encoders(config: &AudioProcessorConfig) -> Result<Arc<HashSet<String>>, AudioError> {
+    static ENCODERS: OnceLock<Mutex<HashMap<PathBuf, Arc<HashSet<String>>>>> = OnceLock::new();
+    let binary = config.ffmpeg_binary();
+    let cache = ENCODERS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(encoders) = cache.lock().ok().and_then(|cache| cache.get(&binary).cloned()) {
+        return Ok(encoders);
+    }
+    let output = command(config, "ffmpeg")
+        .args(["-hide_banner", "-encoders"])
+        .output()
+        .map_err(|e| spawn_error(config, "ffmpeg", e))?;
+    if !output.status.success() {
+        return Err(failure("list encoders", output.status, &output.stderr));
+    }
+    // Entries follow a dashed separator line, as ` A....D name  description`.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let encoders: HashSet<String> = stdout
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1).map(str::to_string))
+        .collect();
+    let encoders = Arc::new(encoders);
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(binary, Arc::clone(&encoders));
+    }
+    Ok(encoders)
+}
+
+/// Fails with `UnsupportedFormat` if the configured ffmpeg has no `encoder`.
+pub(crate) fn require_encoder(config: &AudioProcessorConfig, encoder: &str) -> Result<(), AudioError> {
+    if encoder == "copy" || encoders(config)?.contains(encoder) {
+        return Ok(());
+    }
+    Err(AudioError::UnsupportedFormat(format!(
+        "{} has no '{}' encoder; install an ffmpeg build with it enabled or pick another format/codec",
+        config.ffmpeg_binary().display(),
+        encoder
+    )))
+}
+
+/// Most stderr kept in `AudioError::FfmpegFailed`; the end of the log holds the error.
+const STDERR_LIMIT: usize = 8 * 1024;
+
+/// Builds the error for a process that exited unsuccessfully, keeping the tail of its stderr.
+fn failure(operation: &str, status: ExitStatus, stderr: &[u8]) -> AudioError {
+    let start = stderr.len().saturating_sub(STDERR_LIMIT);
+    let mut text = String::from_utf8_lossy(&stderr[start..]).trim().to_string();
+    if start > 0 {
+        text.insert_str(0, "...");
+    }
+    AudioError::FfmpegFailed { operation: operation.to_string(), stderr: text, exit_code: status.code() }
+}
+
+/// Decodes an input to raw interleaved PCM in the given sample format (e.g. `s16le`, `f32le`)
+/// and returns the bytes ffmpeg wrote to stdout. `input_opts` are placed before `-i`, so
+/// they can carry a `-ss`/`-to` window, and `extra_args` before the output format, so
+/// they can carry `-ac`/`-ar` conversions.
+pub(crate) fn decode_pcm(
+    config: &AudioProcessorConfig,
+    input_path: &str,
+    input_opts: &[&str],
+    sample_format: &str,
+    extra_args: &[&str],
+) -> Result<Vec<u8>, AudioError> {
+    let output = command(config, "ffmpeg")
+        .args(["-v", "error"])
+        .args(config.input_options())
+        .args(input_opts)
+        .args(["-i", input_path])
+        .args(extra_args)
+        .args(["-f", sample_format, "pipe:1"])
+        .output()
+        .map_err(|e| spawn_error(config, "ffmpeg", e))?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(failure("pcm decode", output.status, &output.stderr))
+    }
+}
+
+/// Decodes like `decode_pcm` to interleaved 32-bit float samples.
+pub(crate) fn decode_f32(
+    config: &AudioProcessorConfig,
+    input_path: &str,
+    input_opts: &[&str],
+    extra_args: &[&str],
+) -> Result<Vec<f32>, AudioError> {
+    let pcm = decode_pcm(config, input_path, input_opts, "f32le", extra_args)?;
+    Ok(pcm
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect())
+}
+
+/// Decodes like `decode_pcm`, but hands stdout to `on_chunk` as it arrives instead of
+/// collecting it, so memory stays flat however long the input is. Chunk boundaries can
+/// fall inside a sample.
+pub(crate) fn stream_pcm(
+    config: &AudioProcessorConfig,
+    input_path: &str,
+    sample_format: &str,
+    extra_args: &[&str],
+    mut on_chunk: impl FnMut(&[u8]),
+) -> Result<(), AudioError> {
+    let mut child = command(config, "ffmpeg")
+        .args(["-v", "error"])
+        .args(config.input_options())
+        .args(["-i", input_path])
+        .args(extra_args)
+        .args(["-f", sample_format, "pipe:1"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(config, "ffmpeg", e))?;
+    let mut stderr = child.stderr.take().expect("child stderr is piped");
+    // Drain stderr alongside stdout so a chatty ffmpeg can't block on a full pipe.
+    let stderr = thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = stderr.read_to_end(&mut bytes);
+        bytes
+    });
+    let mut stdout = child.stdout.take().expect("child stdout is piped");
+    let mut buffer = vec![0u8; 64 * 1024];
+    let read = loop {
+        match stdout.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(len) => on_chunk(&buffer[..len]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => break Err(AudioError::IoError(e)),
+        }
+    };
+    if read.is_err() {
+        let _ = child.kill();
+    }
+    let status = child.wait().map_err(AudioError::IoError)?;
+    let stderr = stderr.join().unwrap_or_default();
+    read?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(failure("pcm decode", status, &stderr))
+    }
+}
+
+/// Runs ffprobe for the given `-show_entries` selection and returns its trimmed stdout.
+/// Values are printed one per line without keys (`-of default=nw=1:nk=1`).
+pub(crate) fn probe(config: &AudioProcessorConfig, input_path: &str, entries: &str, extra_args: &[&str]) -> Result<String, AudioError> {
+    let output = command(config, "ffprobe")
+        .args(["-v", "error"])
+        .args(config.input_options())
+        .args(extra_args)
+        .args(["-show_entries", entries, "-of", "default=nw=1:nk=1", input_path])
+        .output()
+        .map_err(|e| spawn_error(config, "ffprobe", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(failure(&format!("probe {}", entries), output.status, &output.stderr))
+    }
+}
+
+/// Runs ffprobe with `-of json`, reporting the sections `show_args` turn on (e.g.
+/// `-show_format`), and returns the JSON document.
+pub(crate) fn probe_json(config: &AudioProcessorConfig, input_path: &str, show_args: &[&str]) -> Result<String, AudioError> {
+    let output = command(config, "ffprobe")
+        .args(["-v", "error"])
+        .args(config.input_options())
+        .args(show_args)
+        .args(["-of", "json", input_path])
+        .output()
+        .map_err(|e| spawn_error(config, "ffprobe", e))?;
+    if !output.status.success() {
+        return Err(failure("probe", output.status, &output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs ffprobe for the tags selected by `entries` (e.g. `format_tags`) and returns them
+/// as `(key, value)` pairs, in the order ffprobe prints them.
+pub(crate) fn probe_tags(config: &AudioProcessorConfig, input_path: &str, entries: &str, extra_args: &[&str]) -> Result<Vec<(String, String)>, AudioError> {
+    let output = command(config, "ffprobe")
+        .args(["-v", "error"])
+        .args(config.input_options())
+        .args(extra_args)
+        .args(["-show_entries", entries, "-of", "default=nw=1", input_path])
+        .output()
+        .map_err(|e| spawn_error(config, "ffprobe", e))?;
+    if !output.status.success() {
+        return Err(failure(&format!("probe {}", entries), output.status, &output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix("TAG:")?.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect())
+}
+
+/// A reader whose bytes are streamed to ffmpeg's stdin (`pipe:0`).
+/// The stream can only be consumed once, by the first operation that runs.
+#[derive(Clone)]
+pub(crate) struct PipeInput {
+    pub(crate) format: &'static str,
+    reader: Arc<Mutex<Option<Box<dyn Read + Send>>>>,
+}
+
+impl PipeInput {
+    pub(crate) fn new(format: &'static str, reader: Box<dyn Read + Send>) -> Self {
+        PipeInput { format, reader: Arc::new(Mutex::new(Some(reader))) }
+    }
+
+    fn take(&self) -> Result<Box<dyn Read + Send>, AudioError> {
+        self.reader
+            .lock()
+            .map_err(|_| AudioError::InvalidParameter("stdin input lock was poisoned".to_string()))?
+            .take()
+            .ok_or_else(|| AudioError::InvalidParameter("stdin input has already been consumed".to_string()))
+    }
+}
+
+impl fmt::Debug for PipeInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipeInput").field("format", &self.format).finish_non_exhaustive()
+    }
+}
+
+/// Runs ffmpeg with `args`, whose last element is the output target.
+/// With `audio_only` enabled, video and subtitle streams are dropped from every output.
+/// With `atomic_output` enabled, each file output is written to a hidden temp file in the
+/// same directory and renamed into place only after ffmpeg succeeds; see `prepare`.
+/// `operation` names the step in the error returned on a non-zero exit.
+pub(crate) fn run(config: &AudioProcessorConfig, args: &[&str], stdin: Option<&PipeInput>, operation: &str) -> Result<(), AudioError> {
+    let (args, staging) = prepare(config, args)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let guard = PartialOutput::watch(config, &args, &staging);
+    let result = spawn(config, &args, stdin, operation);
+    guard.cleanup_if(result.is_err());
+    result?;
+    finish(staging)
+}
+
+/// Runs ffmpeg like `run`, but on tokio's process support so the caller's task yields
+/// while ffmpeg works. The child is killed if the returned future is dropped.
+/// Stdin input isn't supported here, since it is fed from a blocking reader.
+#[cfg(feature = "async")]
+pub(crate) async fn run_async(config: &AudioProcessorConfig, args: &[&str], stdin: Option<&PipeInput>, operation: &str) -> Result<(), AudioError> {
+    if stdin.is_some() {
+        return Err(AudioError::InvalidParameter("stdin input is not supported by async operations".to_string()));
+    }
+    let (args, staging) = prepare(config, args)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let guard = PartialOutput::watch(config, &args, &staging);
+    let result = match tokio::process::Command::from(command(config, "ffmpeg")).args(&args).kill_on_drop(true).output().await {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(failure(operation, output.status, &output.stderr)),
+        Err(e) => Err(spawn_error(config, "ffmpeg", e)),
+    };
+    guard.cleanup_if(result.is_err());
+    result?;
+    finish(staging)
+}
+
+/// Runs ffmpeg like `run` for an output of `pipe:1` and returns what it wrote to stdout.
+/// Stdin is fed on a helper thread, so input and output can stream at the same time.
+pub(crate) fn run_to_stdout(config: &AudioProcessorConfig, args: &[&str], stdin: Option<&PipeInput>, operation: &str) -> Result<Vec<u8>, AudioError> {
+    let (args, _) = prepare(config, args)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    Ok(spawn(config, &args, stdin, operation)?.stdout)
+}
+
+/// Remembers the state of a run's file outputs so they can be removed if the run fails.
+/// Staged outputs need no tracking: staging files delete themselves when dropped.
+struct PartialOutput {
+    /// Each output path or `%d` segment pattern, with the modification times of the
+    /// files it matched before the run.
+    targets: Vec<(PathBuf, HashMap<PathBuf, SystemTime>)>,
+}
+
+impl PartialOutput {
+    fn watch(config: &AudioProcessorConfig, args: &[&str], staging: &Staging) -> Self {
+        let mut targets = Vec::new();
+        if !config.keep_partial_output && staging.is_empty() {
+            for output in output_files(args) {
+                let target = config.resolve_path(output);
+                let previous = matching_files(&target)
+                    .into_iter()
+                    .filter_map(|file| Some((file.clone(), std::fs::metadata(&file).ok()?.modified().ok()?)))
+                    .collect();
+                targets.push((target, previous));
+            }
+        }
+        PartialOutput { targets }
+    }
+
+    /// Deletes the outputs if `failed` and ffmpeg created or rewrote them; pre-existing
+    /// files ffmpeg never touched are left alone.
+    fn cleanup_if(&self, failed: bool) {
+        if !failed {
+            return;
+        }
+        for (target, previous) in &self.targets {
+            for file in matching_files(target) {
+                let modified = std::fs::metadata(&file).ok().and_then(|metadata| metadata.modified().ok());
+                if modified.is_some() && modified.as_ref() != previous.get(&file) {
+                    if let Err(e) = std::fs::remove_file(&file) {
+                        log::warn!("could not remove partial output {}: {}", file.display(), e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns every file path the run in `args` writes to: each file output (each target of
+/// a tee muxer output), and the segment pattern given to an HLS muxer.
+fn output_files<S: AsRef<str>>(args: &[S]) -> Vec<String> {
+    let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+    let mut files = Vec::new();
+    let mut start = 0;
+    for index in output_indices(&args) {
+        // Options between the previous output and this one apply to this one.
+        let options = &args[start..index];
+        start = index + 1;
+        let option = |name: &str| options.windows(2).rev().find(|pair| pair[0] == name).map(|pair| pair[1]);
+        if let Some(pattern) = option("-hls_segment_filename") {
+            files.push(pattern.to_string());
+        }
+        let output = args[index];
+        if option("-f") == Some("tee") {
+            files.extend(tee_paths(output).into_iter().filter(|path| is_file_target(path)));
+        } else if is_file_target(output) {
+            files.push(output.to_string());
+        }
+    }
+    files
+}
+
+/// Splits a tee muxer spec (`[f=mp3]a.mp3|[f=wav]b.wav`) into its output paths, dropping
+/// each target's `[...]` options and undoing the muxer's backslash escapes.
+fn tee_paths(spec: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut path = String::new();
+    let mut in_options = false;
+    let mut chars = spec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars.next();
+                if !in_options {
+                    path.extend(escaped);
+                }
+            }
+            '[' if path.is_empty() && !in_options => in_options = true,
+            ']' if in_options => in_options = false,
+            _ if in_options => {}
+            '|' => paths.push(std::mem::take(&mut path)),
+            _ => path.push(c),
+        }
+    }
+    paths.push(path);
+    paths.retain(|path| !path.is_empty());
+    paths
+}
+
+/// Returns the existing files `target` names: the file itself, or for a `%d`-style
+/// segment pattern, every file in its directory whose name fits the pattern.
+fn matching_files(target: &Path) -> Vec<PathBuf> {
+    let name = target.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let Some((prefix, rest)) = name.split_once('%') else {
+        return if target.exists() { vec![target.to_path_buf()] } else { Vec::new() };
+    };
+    let suffix = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    let suffix = suffix.strip_prefix('d').unwrap_or(suffix);
+    let dir = target.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(suffix))
+                .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Each staged output and the final path it is renamed to on success.
+type Staging = Vec<(tempfile::TempPath, String)>;
+
+/// Options the crate passes that take no value; every other option consumes the next argument.
+const VALUELESS_OPTIONS: [&str; 10] = ["-y", "-n", "-vn", "-sn", "-an", "-dn", "-nostdin", "-nostats", "-hide_banner", "-shortest"];
+
+/// Muxers that write files of their own besides, or instead of, the named output.
+const MULTI_FILE_MUXERS: [&str; 6] = ["hls", "dash", "segment", "ssegment", "stream_segment", "tee"];
+
+/// Returns the indices of the outputs in `args`: every argument that is neither an
+/// option nor an option's value.
+fn output_indices<S: AsRef<str>>(args: &[S]) -> Vec<usize> {
+    let mut outputs = Vec::new();
+    let mut index = 0;
+    while index < args.len() {
+        let arg = args[index].as_ref();
+        if arg.len() > 1 && arg.starts_with('-') {
+            index += if VALUELESS_OPTIONS.contains(&arg) { 1 } else { 2 };
+        } else {
+            outputs.push(index);
+            index += 1;
+        }
+    }
+    outputs
+}
+
+/// Returns true if the run writes files that aren't named by an output argument: a
+/// `%d` pattern expanded per segment, or a muxer such as HLS that writes segments or
+/// fans out to several files. Renaming the named output alone can't make those atomic.
+fn writes_unnamed_files(args: &[String], outputs: &[usize]) -> bool {
+    outputs.iter().any(|&index| args[index].contains('%'))
+        || args.windows(2).any(|pair| pair[0] == "-f" && MULTI_FILE_MUXERS.contains(&pair[1].as_str()))
+}
+
+/// Applies the config's output settings to `args`. With `audio_only`, `-vn -sn` is
+/// added before every output. With `atomic_output`, every file output is swapped for
+/// its own staging path, unless the run also writes files it doesn't name (segment
+/// patterns, HLS, tee), in which case nothing is staged.
+fn prepare(config: &AudioProcessorConfig, args: &[&str]) -> Result<(Vec<String>, Staging), AudioError> {
+    let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let outputs = output_indices(&args);
+    let stage = config.atomic_output && !writes_unnamed_files(&args, &outputs);
+    let mut staging = Vec::new();
+    // Walk backwards so inserting options doesn't shift the outputs still to visit.
+    for &index in outputs.iter().rev() {
+        if stage && is_file_target(&args[index]) {
+            let output = config.resolve_path(&args[index]).to_string_lossy().into_owned();
+            let staged = staging_path(&output)?;
+            args[index] = staged.to_string_lossy().into_owned();
+            staging.push((staged, output));
+        }
+        if config.audio_only {
+            // Output options must precede the output they apply to.
+            args.splice(index..index, ["-vn".to_string(), "-sn".to_string()]);
+        }
+    }
+    Ok((args, staging))
+}
+
+/// Moves staged outputs to their final paths.
+fn finish(staging: Staging) -> Result<(), AudioError> {
+    for (staged, output) in staging {
+        // Rename is atomic within a filesystem, so the final path never holds a partial file.
+        staged.persist(output).map_err(|e| AudioError::IoError(e.error))?;
+    }
+    Ok(())
+}
+
+/// Returns true for outputs that are files on disk rather than pipes or the null muxer.
+fn is_file_target(output: &str) -> bool {
+    output != "-" && !output.starts_with("pipe:")
+}
+
+/// Reserves a hidden temp file next to `output`, keeping its extension so ffmpeg still
+/// picks the right muxer. The file is removed on drop unless persisted.
+fn staging_path(output: &str) -> Result<tempfile::TempPath, AudioError> {
+    let path = Path::new(output);
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let suffix = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    let file = tempfile::Builder::new()
+        .prefix(".partial-")
+        .suffix(&suffix)
+        .tempfile_in(dir)
+        .map_err(AudioError::IoError)?;
+    Ok(file.into_temp_path())
+}
+
+/// Spawns ffmpeg, streaming `stdin` into the child on a helper thread when given.
+/// Stdout and stderr are captured and returned on success.
+fn spawn(config: &AudioProcessorConfig, args: &[&str], stdin: Option<&PipeInput>, operation: &str) -> Result<Output, AudioError> {
+    let mut command = command(config, "ffmpeg");
+    command.args(args);
+    let output = match stdin {
+        None => command.output().map_err(|e| spawn_error(config, "ffmpeg", e))?,
+        Some(pipe) => {
+            let mut reader = pipe.take()?;
+            let mut child = command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| spawn_error(config, "ffmpeg", e))?;
+            let mut child_stdin = child.stdin.take().expect("child stdin is piped");
+            // Write on a separate thread so a full pipe can't deadlock against ffmpeg's output.
+            let writer = thread::spawn(move || std::io::copy(&mut reader, &mut child_stdin));
+            let output = child.wait_with_output().map_err(AudioError::IoError)?;
+            match writer.join() {
+                // A broken pipe only means ffmpeg stopped reading early; its exit status decides.
+                Ok(Err(e)) if e.kind() != ErrorKind::BrokenPipe => return Err(AudioError::IoError(e)),
+                _ => {}
+            }
+            output
+        }
+    };
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(failure(operation, output.status, &output.stderr))
+    }
+}
+
+/// Runs an analysis filter over the whole input and returns ffmpeg's stderr,
+/// where filters such as `astats`, `ebur128` and `silencedetect` print their results.
+pub(crate) fn analyze(config: &AudioProcessorConfig, input_path: &str, filter: &str, operation: &str) -> Result<String, AudioError> {
+    let output = command(config, "ffmpeg")
+        .args(["-hide_banner", "-nostats"])
+        .args(config.input_options())
+        .args(["-i", input_path, "-af", filter, "-f", "null", "-"])
+        .output()
+        .map_err(|e| spawn_error(config, "ffmpeg", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+    } else {
+        Err(failure(operation, output.status, &output.stderr))
+    }
+}
+
+/// A running ffmpeg process that another thread can cancel while one waits on it.
+/// The child is only locked briefly, so `cancel` never blocks behind `wait`.
+pub(crate) struct Job {
+    child: Mutex<Child>,
+    stderr: Mutex<Option<thread::JoinHandle<Vec<u8>>>>,
+    writer: Mutex<Option<thread::JoinHandle<std::io::Result<u64>>>>,
+    staging: Mutex<Staging>,
+    partial: Mutex<Option<PartialOutput>>,
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+    operation: String,
+}
+
+/// Starts ffmpeg with `args` (as for `run`) without waiting for it to finish.
+pub(crate) fn start(config: &AudioProcessorConfig, args: &[&str], stdin: Option<&PipeInput>, operation: &str) -> Result<Job, AudioError> {
+    let (args, staging) = prepare(config, args)?;
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    let partial = PartialOutput::watch(config, &arg_refs, &staging);
+    let mut command = command(config, "ffmpeg");
+    command.args(&args).stdout(Stdio::null()).stderr(Stdio::piped());
+    if stdin.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let mut child = command.spawn().map_err(|e| spawn_error(config, "ffmpeg", e))?;
+    let writer = match stdin {
+        Some(pipe) => {
+            let mut reader = pipe.take()?;
+            let mut child_stdin = child.stdin.take().expect("child stdin is piped");
+            Some(thread::spawn(move || std::io::copy(&mut reader, &mut child_stdin)))
+        }
+        None => None,
+    };
+    let mut stderr = child.stderr.take().expect("child stderr is piped");
+    let stderr = thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stderr.read_to_end(&mut buffer);
+        buffer
+    });
+    Ok(Job {
+        child: Mutex::new(child),
+        stderr: Mutex::new(Some(stderr)),
+        writer: Mutex::new(writer),
+        staging: Mutex::new(staging),
+        partial: Mutex::new(Some(partial)),
+        cancelled: AtomicBool::new(false),
+        finished: AtomicBool::new(false),
+        operation: operation.to_string(),
+    })
+}
+
+impl Job {
+    /// Kills ffmpeg if it is still running and removes whatever output it wrote.
+    pub(crate) fn cancel(&self) -> Result<(), AudioError> {
+        self.cancelled.store(true, Ordering::SeqCst);
+        let mut child = self.child.lock().map_err(|_| poisoned())?;
+        // Killing a process that has already exited is not an error.
+        child.kill().map_err(AudioError::IoError)?;
+        child.wait().map_err(AudioError::IoError)?;
+        drop(child);
+        self.discard();
+        Ok(())
+    }
+
+    /// Blocks until ffmpeg exits, then moves the output into place. Returns
+    /// `AudioError::Cancelled` if `cancel` was called, even if ffmpeg had already finished.
+    pub(crate) fn wait(&self) -> Result<(), AudioError> {
+        let result = self.wait_for_exit();
+        self.finished.store(true, Ordering::SeqCst);
+        result
+    }
+
+    /// Returns true once `wait` has returned.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    fn wait_for_exit(&self) -> Result<(), AudioError> {
+        // stderr reaches EOF once ffmpeg exits, so wait for it before locking the child.
+        let stderr = self.stderr.lock().map_err(|_| poisoned())?.take();
+        let stderr = stderr.and_then(|reader| reader.join().ok()).unwrap_or_default();
+        let status = self.child.lock().map_err(|_| poisoned())?.wait().map_err(AudioError::IoError)?;
+        if let Some(writer) = self.writer.lock().map_err(|_| poisoned())?.take() {
+            if let Ok(Err(e)) = writer.join() {
+                if e.kind() != ErrorKind::BrokenPipe && !self.cancelled.load(Ordering::SeqCst) {
+                    self.discard();
+                    return Err(AudioError::IoError(e));
+                }
+            }
+        }
+        if self.cancelled.load(Ordering::SeqCst) {
+            self.discard();
+            return Err(AudioError::Cancelled(self.operation.clone()));
+        }
+        if !status.success() {
+            self.discard();
+            return Err(failure(&self.operation, status, &stderr));
+        }
+        let staging = std::mem::take(&mut *self.staging.lock().map_err(|_| poisoned())?);
+        let result = finish(staging);
+        if let Some(partial) = self.partial.lock().map_err(|_| poisoned())?.take() {
+            partial.cleanup_if(result.is_err());
+        }
+        result
+    }
+
+    /// Drops the staged output and deletes any partial file at the final path.
+    fn discard(&self) {
+        if let Ok(mut staging) = self.staging.lock() {
+            staging.clear();
+        }
+        if let Some(partial) = self.partial.lock().ok().and_then(|mut partial| partial.take()) {
+            partial.cleanup_if(true);
+        }
+    }
+}
+
+impl Drop for Job {
+    /// A job dropped before `wait` returned is cancelled, so ffmpeg doesn't outlive it.
+    fn drop(&mut self) {
+        if !self.is_finished() {
+            let _ = self.cancel();
+        }
+    }
+}
+
+fn poisoned() -> AudioError {
+    AudioError::InvalidParameter("ffmpeg job lock was poisoned".to_string())
+}
+
+/// Progress of a running ffmpeg process, read from its `-progress pipe:1` report.
+/// Yields the fraction of `total` encoded so far, never decreasing, and `1.0` once ffmpeg
+/// exits successfully; a failure is yielded as the final error item.
+pub(crate) struct Progress {
+    child: Option<Child>,
+    lines: Option<Lines<BufReader<ChildStdout>>>,
+    writer: Option<thread::JoinHandle<std::io::Result<u64>>>,
+    stderr: Option<thread::JoinHandle<Vec<u8>>>,
+    staging: Staging,
+    partial: Option<PartialOutput>,
+    total_us: f64,
+    last: f32,
+    operation: String,
+    failed: Option<AudioError>,
+}
+
+/// Starts ffmpeg with `args` (as for `run`) and returns an iterator over its progress.
+/// `total` is the expected output duration that progress is measured against.
+pub(crate) fn run_with_progress(
+    config: &AudioProcessorConfig,
+    args: &[&str],
+    stdin: Option<&PipeInput>,
+    total: Duration,
+    operation: &str,
+) -> Progress {
+    let mut progress = Progress::idle(total, operation);
+    if let Err(e) = progress.start(config, args, stdin) {
+        progress.failed = Some(e);
+    }
+    progress
+}
+
+/// Returns a progress iterator that yields `error` and ends, for a run rejected before
+/// ffmpeg was started.
+pub(crate) fn failed_progress(error: AudioError, operation: &str) -> Progress {
+    let mut progress = Progress::idle(Duration::ZERO, operation);
+    progress.failed = Some(error);
+    progress
+}
+
+impl Progress {
+    fn idle(total: Duration, operation: &str) -> Self {
+        Progress {
+            child: None,
+            lines: None,
+            writer: None,
+            stderr: None,
+            staging: Vec::new(),
+            partial: None,
+            total_us: total.as_micros() as f64,
+            last: 0.0,
+            operation: operation.to_string(),
+            failed: None,
+        }
+    }
+
+    fn start(&mut self, config: &AudioProcessorConfig, args: &[&str], stdin: Option<&PipeInput>) -> Result<(), AudioError> {
+        let (args, staging) = prepare(config, args)?;
+        let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.partial = Some(PartialOutput::watch(config, &arg_refs, &staging));
+        self.staging = staging;
+        let mut command = command(config, "ffmpeg");
+        // The periodic stats line would duplicate the report on stderr.
+        command.args(["-progress", "pipe:1", "-nostats"]).args(&args).stdout(Stdio::piped()).stderr(Stdio::piped());
+        if stdin.is_some() {
+            command.stdin(Stdio::piped());
+        }
+        let mut child = command.spawn().map_err(|e| spawn_error(config, "ffmpeg", e))?;
+        if let Some(pipe) = stdin {
+            let mut reader = pipe.take()?;
+            let mut child_stdin = child.stdin.take().expect("child stdin is piped");
+            self.writer = Some(thread::spawn(move || std::io::copy(&mut reader, &mut child_stdin)));
+        }
+        let mut stderr = child.stderr.take().expect("child stderr is piped");
+        // Drain stderr alongside the progress report so neither pipe fills up and blocks ffmpeg.
+        self.stderr = Some(thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = stderr.read_to_end(&mut buffer);
+            buffer
+        }));
+        let stdout = child.stdout.take().expect("child stdout is piped");
+        self.lines = Some(BufReader::new(stdout).lines());
+        self.child = Some(child);
+        Ok(())
+    }
+
+    /// Waits for ffmpeg to exit and moves the output into place.
+    fn finish(&mut self) -> Result<(), AudioError> {
+        let status = match self.child.take() {
+            Some(mut child) => child.wait().map_err(AudioError::IoError)?,
+            None => return Ok(()),
+        };
+        if let Some(writer) = self.writer.take() {
+            match writer.join() {
+                Ok(Err(e)) if e.kind() != ErrorKind::BrokenPipe => return Err(AudioError::IoError(e)),
+                _ => {}
+            }
+        }
+        let stderr = self.stderr.take().and_then(|reader| reader.join().ok()).unwrap_or_default();
+        if !status.success() {
+            return Err(failure(&self.operation, status, &stderr));
+        }
+        finish(std::mem::take(&mut self.staging))
+    }
+}
+
+impl Drop for Progress {
+    /// Abandoning the iterator before ffmpeg exits kills it and removes the partial output.
+    fn drop(&mut self) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        self.staging.clear();
+        if let Some(partial) = self.partial.take() {
+            partial.cleanup_if(true);
+        }
+    }
+}
+
+impl Iterator for Progress {
+    type Item = Result<f32, AudioError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.failed.take() {
+            self.lines = None;
+            return Some(Err(e));
+        }
+        let lines = self.lines.as_mut()?;
+        for line in lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    self.lines = None;
+                    return Some(Err(AudioError::IoError(e)));
+                }
+            };
+            // Both keys carry microseconds, whatever the name of the older one suggests.
+            let value = line.strip_prefix("out_time_us=").or_else(|| line.strip_prefix("out_time_ms="));
+            let Some(out_time_us) = value.and_then(|value| value.trim().parse::<f64>().ok()) else {
+                continue;
+            };
+            if self.total_us > 0.0 {
+                // Stay below 1.0 until ffmpeg has actually exited successfully.
+                let fraction = ((out_time_us / self.total_us) as f32).clamp(0.0, 0.999);
+                if fraction > self.last {
+                    self.last = fraction;
+                    return Some(Ok(fraction));
+                }
+            }
+        }
+        self.lines = None;
+        let result = self.finish();
+        if let Some(partial) = self.partial.take() {
+            partial.cleanup_if(result.is_err());
+        }
+        Some(result.map(|()| 1.0))
+    }
+}
+
+/// Checks that `graph` parses and links by running it over a few milliseconds of generated
+/// silence. `complex` selects `-filter_complex` rather than `-af`. Errors carry ffmpeg's
+/// own explanation.
+pub(crate) fn validate_filtergraph(config: &AudioProcessorConfig, graph: &str, complex: bool) -> Result<(), AudioError> {
+    let filter_flag = if complex { "-filter_complex" } else { "-af" };
+    let output = command(config, "ffmpeg")
+        .args(["-v", "error", "-f", "lavfi", "-i", "anullsrc", "-t", "0.01"])
+        .args([filter_flag, graph, "-f", "null", "-"])
+        .output()
+        .map_err(|e| spawn_error(config, "ffmpeg", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(failure(&format!("filtergraph validation of '{}'", graph), output.status, &output.stderr))
+    }
+}
@@ -1,12 +1,17 @@
+use crate::config::AudioProcessorConfig;
 use crate::errors::AudioError;
+use crate::{ffmpeg, AudioProcessor};
+use serde::Deserialize;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
 
 /// Loads an audio file (here we simply check its existence).
 pub fn load_audio(file_path: &str) -> Result<(), AudioError> {
     println!("Loading audio from file: {}", file_path);
     // In a real integration, you might initialize FFmpeg contexts here.
-    File::open(file_path).map_err(|e| AudioError::IoError(e))?;
+    File::open(file_path).map_err(AudioError::IoError)?;
     Ok(())
 }
 
@@ -14,7 +19,286 @@ pub fn load_audio(file_path: &str) -> Result<(), AudioError> {
 pub fn save_audio(file_path: &str) -> Result<(), AudioError> {
     println!("Saving audio to file: {}", file_path);
     // This function is not used directly when FFmpeg writes output files.
-    let mut file = File::create(file_path).map_err(|e| AudioError::IoError(e))?;
-    file.write_all(b"dummy audio data").map_err(|e| AudioError::IoError(e))?;
+    let mut file = File::create(file_path).map_err(AudioError::IoError)?;
+    file.write_all(b"dummy audio data").map_err(AudioError::IoError)?;
     Ok(())
 }
+
+/// The format fields of a WAV file's `fmt ` chunk, plus the size of its `data` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavHeader {
+    /// WAVE format tag: 1 for integer PCM, 3 for IEEE float, 0xFFFE for extensible.
+    pub audio_format: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    /// Length of the sample data in bytes.
+    pub data_len: u32,
+}
+
+/// Technical details of a file's first audio stream, from `AudioProcessor::info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInfo {
+    pub duration: Duration,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// FFmpeg's name for the stream's codec, e.g. `pcm_s16le` or `mp3`.
+    pub codec: String,
+    /// Bits per second of the stream, or of the whole file if the stream doesn't say.
+    /// `None` when neither is recorded.
+    pub bitrate: Option<u32>,
+}
+
+/// The parts of `ffprobe -of json -show_streams -show_format` output `FileInfo` uses.
+/// ffprobe prints most numbers as strings and omits fields it doesn't know.
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_name: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u16>,
+    bit_rate: Option<String>,
+    duration: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    bit_rate: Option<String>,
+    duration: Option<String>,
+}
+
+/// Builds a `FileInfo` from ffprobe's JSON for a single audio stream. The duration is
+/// `None` when neither the container nor the stream records one.
+pub(crate) fn parse_file_info(json: &str) -> Result<(FileInfo, Option<Duration>), AudioError> {
+    let probe: ProbeOutput = serde_json::from_str(json)
+        .map_err(|e| AudioError::InvalidParameter(format!("could not parse ffprobe output: {}", e)))?;
+    let stream = probe
+        .streams
+        .into_iter()
+        .next()
+        .ok_or_else(|| AudioError::InvalidParameter("input has no audio stream".to_string()))?;
+    let format = probe.format.unwrap_or(ProbeFormat { bit_rate: None, duration: None });
+    let number = |value: Option<&String>| value.and_then(|value| value.trim().parse::<f64>().ok());
+    let duration = number(format.duration.as_ref())
+        .or_else(|| number(stream.duration.as_ref()))
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64);
+    let info = FileInfo {
+        duration: duration.unwrap_or_default(),
+        sample_rate: number(stream.sample_rate.as_ref())
+            .map(|rate| rate as u32)
+            .ok_or_else(|| AudioError::InvalidParameter("audio stream has no sample rate".to_string()))?,
+        channels: stream
+            .channels
+            .ok_or_else(|| AudioError::InvalidParameter("audio stream has no channel count".to_string()))?,
+        codec: stream.codec_name.unwrap_or_default(),
+        bitrate: number(stream.bit_rate.as_ref()).or_else(|| number(format.bit_rate.as_ref())).map(|rate| rate as u32),
+    };
+    Ok((info, duration))
+}
+
+/// Reads the header of a RIFF/WAVE file without decoding it.
+/// Chunks before `data` other than `fmt ` (e.g. `LIST`) are skipped.
+pub fn read_wav_header(path: &str) -> Result<WavHeader, AudioError> {
+    let mut reader = BufReader::new(File::open(path).map_err(AudioError::IoError)?);
+    let mut riff = [0u8; 12];
+    reader.read_exact(&mut riff).map_err(AudioError::IoError)?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err(AudioError::InvalidParameter(format!("{} is not a WAV file", path)));
+    }
+    let mut format = None;
+    let mut chunk_header = [0u8; 8];
+    loop {
+        reader.read_exact(&mut chunk_header).map_err(AudioError::IoError)?;
+        let size = u32::from_le_bytes([chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7]]);
+        match &chunk_header[0..4] {
+            b"fmt " => {
+                let mut data = vec![0u8; size as usize];
+                reader.read_exact(&mut data).map_err(AudioError::IoError)?;
+                if data.len() < 16 {
+                    return Err(AudioError::InvalidParameter(format!("{} has a truncated fmt chunk", path)));
+                }
+                format = Some((
+                    u16::from_le_bytes([data[0], data[1]]),
+                    u16::from_le_bytes([data[2], data[3]]),
+                    u32::from_le_bytes([data[4], data[5], data[6], data[7]]),
+                    u16::from_le_bytes([data[14], data[15]]),
+                ));
+                if size % 2 == 1 {
+                    reader.seek(SeekFrom::Current(1)).map_err(AudioError::IoError)?;
+                }
+            }
+            b"data" => break,
+            // Chunks are padded to an even length.
+            _ => {
+                reader.seek(SeekFrom::Current(i64::from(size + size % 2))).map_err(AudioError::IoError)?;
+            }
+        }
+    }
+    let data_len = u32::from_le_bytes([chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7]]);
+    let (audio_format, channels, sample_rate, bits_per_sample) = format.ok_or_else(|| {
+        AudioError::InvalidParameter(format!("{} has no fmt chunk before its data", path))
+    })?;
+    Ok(WavHeader { audio_format, channels, sample_rate, bits_per_sample, data_len })
+}
+
+/// Writes interleaved samples in -1.0..=1.0 (frame by frame, e.g. L R L R for stereo) to a
+/// 16-bit PCM WAV file, without invoking FFmpeg. Out-of-range samples are clipped.
+pub fn write_wav(path: &str, samples: &[f32], sample_rate: u32, channels: u8) -> Result<(), AudioError> {
+    if channels == 0 || sample_rate == 0 {
+        return Err(AudioError::InvalidParameter(format!(
+            "WAV needs at least one channel and a non-zero sample rate, got {} channels at {} Hz",
+            channels, sample_rate
+        )));
+    }
+    if !samples.len().is_multiple_of(usize::from(channels)) {
+        return Err(AudioError::InvalidParameter(format!(
+            "{} samples don't divide into whole frames of {} channels", samples.len(), channels
+        )));
+    }
+    let data_len = u32::try_from(samples.len() * 2)
+        .ok()
+        .filter(|len| *len <= u32::MAX - 36)
+        .ok_or_else(|| AudioError::InvalidParameter("too many samples for a WAV file".to_string()))?;
+    let block_align = u16::from(channels) * 2;
+
+    let mut writer = BufWriter::new(File::create(path).map_err(AudioError::IoError)?);
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&u16::from(channels).to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    writer.write_all(&header).map_err(AudioError::IoError)?;
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)).round() as i16;
+        writer.write_all(&value.to_le_bytes()).map_err(AudioError::IoError)?;
+    }
+    writer.flush().map_err(AudioError::IoError)
+}
+
+/// Synthesizes a mono metronome track of `beats` clicks at `bpm` and writes it as a
+/// 16-bit WAV, without invoking FFmpeg. Each click is a 20 ms 1 kHz blip with a fast
+/// decay; the track lasts exactly `beats` beat lengths.
+pub fn generate_click_track(bpm: f32, beats: u32, sample_rate: u32, output_path: &str) -> Result<AudioProcessor, AudioError> {
+    if !bpm.is_finite() || bpm <= 0.0 {
+        return Err(AudioError::InvalidParameter(format!("bpm must be positive, got {}", bpm)));
+    }
+    if beats == 0 {
+        return Err(AudioError::InvalidParameter("a click track needs at least one beat".to_string()));
+    }
+    if sample_rate == 0 {
+        return Err(AudioError::InvalidParameter("sample rate must be non-zero".to_string()));
+    }
+    let beat_len = 60.0 / f64::from(bpm) * f64::from(sample_rate);
+    let total = (beat_len * f64::from(beats)).round() as usize;
+    let click_len = (f64::from(sample_rate) * 0.02) as usize;
+    let mut samples = vec![0.0f32; total];
+    for beat in 0..beats {
+        let start = (beat_len * f64::from(beat)).round() as usize;
+        for (offset, sample) in samples.iter_mut().skip(start).take(click_len).enumerate() {
+            let t = offset as f32 / sample_rate as f32;
+            *sample = 0.8 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin() * (-t * 250.0).exp();
+        }
+    }
+    write_wav(output_path, &samples, sample_rate, 1)?;
+    println!("Generated {} beat click track at {} BPM -> {}", beats, bpm, output_path);
+    AudioProcessor::new(output_path)
+}
+
+/// Spectral shape of the noise from `generate_noise`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseColor {
+    /// Equal energy per frequency.
+    White,
+    /// Energy falling 3 dB per octave, equal per octave; sounds balanced to the ear.
+    Pink,
+    /// Energy falling 6 dB per octave; a deep rumble.
+    Brown,
+}
+
+impl NoiseColor {
+    /// Returns the name anoisesrc's `color` option takes.
+    fn name(&self) -> &'static str {
+        match self {
+            NoiseColor::White => "white",
+            NoiseColor::Pink => "pink",
+            NoiseColor::Brown => "brown",
+        }
+    }
+}
+
+/// Renders `duration` of mono noise of the given `color` at `sample_rate` to
+/// `output_path` with FFmpeg's anoisesrc source.
+pub fn generate_noise(color: NoiseColor, duration: Duration, sample_rate: u32, output_path: &str) -> Result<AudioProcessor, AudioError> {
+    if duration.is_zero() {
+        return Err(AudioError::InvalidParameter("noise duration must be greater than zero".to_string()));
+    }
+    if sample_rate == 0 {
+        return Err(AudioError::InvalidParameter("sample rate must be non-zero".to_string()));
+    }
+    let source = format!("anoisesrc=color={}:sample_rate={}:duration={}", color.name(), sample_rate, duration.as_secs_f64());
+    ffmpeg::run(&AudioProcessorConfig::default(), &["-y", "-f", "lavfi", "-i", &source, output_path], None, "generate noise")?;
+    println!("Generated {:?} of {} noise -> {}", duration, color.name(), output_path);
+    AudioProcessor::new(output_path)
+}
+
+/// Audio sources FFmpeg's lavfi device can generate without an input file.
+const LAVFI_SOURCES: [&str; 6] = ["anullsrc", "sine", "aevalsrc", "anoisesrc", "afirsrc", "sinc"];
+
+/// Returns true if `path` is a URL such as `https://host/a.mp3` or `rtmp://host/live`.
+pub(crate) fn is_url(path: &str) -> bool {
+    match path.split_once("://") {
+        // Require a multi-character scheme so Windows drive letters aren't mistaken for URLs.
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// Returns true if `path` is a lavfi source description (e.g. `sine=frequency=440`)
+/// rather than a file on disk.
+pub(crate) fn is_lavfi_source(path: &str) -> bool {
+    let name = path.split(['=', ':', ',']).next().unwrap_or_default();
+    LAVFI_SOURCES.contains(&name) && !Path::new(path).exists()
+}
+
+/// Returns `path` as UTF-8 for passing to ffmpeg. Non-UTF-8 paths, which Linux allows
+/// (e.g. under an unusual `TMPDIR`), fail with `InvalidParameter` rather than being
+/// mangled by a lossy conversion.
+pub(crate) fn path_str(path: &Path) -> Result<&str, AudioError> {
+    path.to_str().ok_or_else(|| {
+        AudioError::InvalidParameter(format!("path {} is not valid UTF-8", path.display()))
+    })
+}
+
+/// Returns the concat demuxer list line for `path`. Single quotes are escaped as `'\''`
+/// (close the quoted string, an escaped quote, reopen), so any file name round-trips.
+pub(crate) fn concat_list_entry(path: &str) -> String {
+    format!("file '{}'", path.replace('\'', "'\\''"))
+}
+
+/// Cheaply checks whether FFmpeg can be pointed at `path`, without opening or probing it.
+/// URLs are assumed reachable (FFmpeg reports network errors when it connects), lavfi
+/// source strings are always available, and anything else must exist on disk. Symlinks
+/// are not followed, so a link on a slow network mount costs one local lookup; FFmpeg
+/// reports a dangling link when it opens it.
+pub fn input_is_accessible(path: &str) -> bool {
+    is_url(path) || is_lavfi_source(path) || std::fs::symlink_metadata(path).is_ok()
+}
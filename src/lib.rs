@@ -2,205 +2,1986 @@ pub mod io;
 pub mod processing;
 pub mod transcoding;
 pub mod errors;
+pub mod config;
+pub mod analysis;
+pub mod tags;
+pub mod watch;
+pub mod operation;
+mod ffmpeg;
+#[cfg(feature = "async")]
+mod asynchronous;
 
 use std::time::Duration;
+use crate::analysis::{LevelStats, SilenceReport};
+use crate::config::{AudioProcessorConfig, ClippingPolicy, MergeMismatchPolicy};
 use crate::errors::AudioError;
-use crate::transcoding::AudioFormat;
-use crate::processing::{AudioEffect, effect_to_filter};
+use crate::ffmpeg::PipeInput;
+use crate::io::FileInfo;
+use crate::operation::Operation;
+use crate::transcoding::{AudioFormat, QualityTarget, TranscodeHandle, TranscodeOptions};
+use crate::processing::{AudioEffect, CompressorParams, NormalizeMode, atempo_chain, db_to_linear, effect_to_filter, fade_out_filter, linear_to_db, pitch_shift_filter, envelope_to_filter, parse_envelope, timeline_filter, validate_effect, volume_db_filter, volume_filter};
+
+/// Headroom `mix` leaves below 0 dBFS.
+const DEFAULT_MIX_HEADROOM_DB: f32 = 3.0;
+
+/// Sample rate recordings are decoded at for cross-correlation; 8 kHz keeps the
+/// transform small while still resolving offsets to 0.125 ms.
+const ALIGNMENT_SAMPLE_RATE: u32 = 8000;
+
+/// Channel correlation below which `fix_phase` treats a recording as polarity-inverted.
+/// Genuine wide stereo rarely drops under zero, let alone this far.
+const PHASE_INVERSION_THRESHOLD: f32 = -0.5;
+
+/// How far, per input, a stream-copied merge may drift from the inputs' summed duration
+/// before `merge_audios` re-encodes it; covers the frame padding of codecs like MP3.
+const MERGE_DURATION_TOLERANCE: Duration = Duration::from_millis(100);
+
+/// Block length `apply_compressor_metered` compares RMS levels over; short enough to catch
+/// the gain reduction on a single drum hit.
+const COMPRESSOR_METER_WINDOW: Duration = Duration::from_millis(50);
 
 /// Main struct for processing an audio file.
 #[derive(Debug, Clone)]
 pub struct AudioProcessor {
     pub file_path: String,
+    pub config: AudioProcessorConfig,
+    pipe_input: Option<PipeInput>,
+    /// Temp file backing a processor from `from_lavfi`, deleted with its last clone.
+    _generated_input: Option<std::sync::Arc<tempfile::TempPath>>,
 }
 
 impl AudioProcessor {
-    /// Creates a new audio processor instance from a file path.
+    /// Creates a new audio processor instance from a file path, URL or lavfi source string.
+    /// Fails with `FfmpegNotFound` if ffmpeg can't be started.
     pub fn new(file_path: &str) -> Result<Self, AudioError> {
-        // Check if file exists; real FFmpeg initialization could be done here.
-        std::fs::metadata(file_path).map_err(|e| AudioError::IoError(e))?;
+        Self::with_config(file_path, AudioProcessorConfig::default())
+    }
+
+    /// Creates a new audio processor instance with a custom configuration.
+    /// Relative paths are checked against the config's `working_dir`, if set.
+    pub fn with_config(file_path: &str, config: AudioProcessorConfig) -> Result<Self, AudioError> {
+        // URLs and lavfi sources aren't files, so only regular paths must exist here.
+        if !config.input_is_accessible(file_path) {
+            return Err(AudioError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("input {} is not accessible", file_path),
+            )));
+        }
+        ffmpeg::ensure_available(&config)?;
         println!("Initializing audio processor for file: {}", file_path);
-        io::load_audio(file_path)?;
-        Ok(AudioProcessor {
-            file_path: file_path.to_string(),
+        Ok(AudioProcessor { file_path: file_path.to_string(), config, pipe_input: None, _generated_input: None })
+    }
+
+    /// Creates a new audio processor that runs the ffmpeg binary at `ffmpeg_path` instead
+    /// of the one on `PATH`. Processors derived from it, and `merge_audios`/`mix` calls
+    /// that take it as their first input, use the same binary.
+    pub fn with_ffmpeg_path(file_path: &str, ffmpeg_path: &str) -> Result<Self, AudioError> {
+        let config = AudioProcessorConfig { ffmpeg_path: Some(ffmpeg_path.into()), ..AudioProcessorConfig::default() };
+        Self::with_config(file_path, config)
+    }
+
+    /// Creates a processor that reads its input from ffmpeg's stdin (`pipe:0`).
+    /// The bytes of `reader` are written to ffmpeg on a helper thread, and `format_hint`
+    /// tells ffmpeg how to demux them since a pipe has no extension to probe.
+    /// The stream is consumed by the first operation, and probing methods such as
+    /// `duration` need a file, so transcode the stream to disk before further processing.
+    pub fn from_stdin(format_hint: AudioFormat, reader: impl std::io::Read + Send + 'static) -> Self {
+        println!("Initializing audio processor for stdin input ({:?})", format_hint);
+        AudioProcessor {
+            file_path: "pipe:0".to_string(),
+            config: AudioProcessorConfig::default(),
+            pipe_input: Some(PipeInput::new(format_hint.format_name(), Box::new(reader))),
+            _generated_input: None,
+        }
+    }
+
+    /// Creates a processor for audio held in memory, such as an upload, in `input_format`.
+    /// The bytes are piped to ffmpeg's stdin like `from_stdin`, so nothing touches disk,
+    /// and they are likewise consumed by the first operation; see `transcode_to_bytes`.
+    pub fn from_bytes(data: Vec<u8>, input_format: AudioFormat) -> Self {
+        Self::from_stdin(input_format, std::io::Cursor::new(data))
+    }
+
+    /// Creates a processor from a lavfi source such as `sine=frequency=440` or
+    /// `anullsrc=r=44100:cl=stereo`, limited to `duration`. The source is rendered to a
+    /// temp WAV file up front, so probing methods like `duration` work on it; the file
+    /// is deleted when the processor and its clones are dropped.
+    pub fn from_lavfi(source: &str, duration: Duration) -> Result<Self, AudioError> {
+        if duration.is_zero() {
+            return Err(AudioError::InvalidParameter("lavfi source duration must be greater than zero".to_string()));
+        }
+        let config = AudioProcessorConfig::default();
+        ffmpeg::ensure_available(&config)?;
+        let temp = tempfile::Builder::new()
+            .prefix("lavfi-")
+            .suffix(".wav")
+            .tempfile()
+            .map_err(AudioError::IoError)?
+            .into_temp_path();
+        let file_path = io::path_str(&temp)?.to_string();
+        let secs = duration.as_secs_f64().to_string();
+        ffmpeg::run(&config, &["-y", "-f", "lavfi", "-i", source, "-t", &secs, &file_path], None, "lavfi source")?;
+        println!("Initializing audio processor for lavfi source {} ({:?}) -> {}", source, duration, file_path);
+        Ok(AudioProcessor { file_path, config, pipe_input: None, _generated_input: Some(std::sync::Arc::new(temp)) })
+    }
+
+    /// Builds the processor for an operation's output, carrying over this processor's config.
+    fn derived(&self, file_path: String) -> Self {
+        AudioProcessor { file_path, config: self.config.clone(), pipe_input: None, _generated_input: None }
+    }
+
+    /// Returns the default output path for an operation: the input's file name with
+    /// `prefix_` prepended, in the input's directory. The `_to` variants bypass it.
+    fn prefixed_output(&self, prefix: &str) -> String {
+        let path = std::path::Path::new(&self.file_path);
+        let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        path.with_file_name(format!("{}_{}", prefix, name)).to_string_lossy().into_owned()
+    }
+
+    /// Runs ffmpeg against this processor's input.
+    /// `input_opts` are placed before `-i` (e.g. `-ss` for fast seeking) and `output_args`
+    /// after it; `output_args` must end with the output target.
+    fn run_ffmpeg(&self, input_opts: &[&str], output_args: &[&str], operation: &str) -> Result<(), AudioError> {
+        let args = self.ffmpeg_args(input_opts, output_args);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        ffmpeg::run(&self.config, &args, self.pipe_input.as_ref(), operation)
+    }
+
+    /// Builds the ffmpeg arguments for `run_ffmpeg`.
+    fn ffmpeg_args(&self, input_opts: &[&str], output_args: &[&str]) -> Vec<String> {
+        let mut args = vec!["-y".to_string()];
+        args.extend(self.config.input_options());
+        args.extend(input_opts.iter().map(|arg| arg.to_string()));
+        if let Some(pipe) = &self.pipe_input {
+            args.extend(["-f".to_string(), pipe.format.to_string()]);
+        } else if io::is_lavfi_source(&self.file_path) {
+            args.extend(["-f".to_string(), "lavfi".to_string()]);
+        }
+        args.extend(["-i".to_string(), self.file_path.clone()]);
+        args.extend(output_args.iter().map(|arg| arg.to_string()));
+        if !output_args.contains(&"-map_metadata") {
+            // Placed just before the output target, since `output_args` may open further
+            // inputs (e.g. an overlay) that an earlier output option would attach to.
+            let source = if self.config.preserve_metadata { "0" } else { "-1" };
+            let target = args.len() - usize::from(!output_args.is_empty());
+            args.splice(target..target, ["-map_metadata".to_string(), source.to_string()]);
+        }
+        args
+    }
+
+    /// Returns the duration of the audio as reported by ffprobe.
+    /// Containers that don't record a format-level duration fall back to the first audio
+    /// stream's duration, and failing that to an estimate from file size and bitrate.
+    /// A missing ffprobe fails with `FfmpegNotFound`; output none of those steps can parse
+    /// fails with `InvalidParameter`.
+    pub fn duration(&self) -> Result<Duration, AudioError> {
+        let format = ffmpeg::probe(&self.config, &self.file_path, "format=duration", &[])?;
+        if let Some(secs) = parse_secs(&format) {
+            return Ok(Duration::from_secs_f64(secs));
+        }
+        let stream = ffmpeg::probe(&self.config, &self.file_path, "stream=duration", &["-select_streams", "a:0"])?;
+        if let Some(secs) = parse_secs(&stream) {
+            return Ok(Duration::from_secs_f64(secs));
+        }
+        let size = ffmpeg::probe(&self.config, &self.file_path, "format=size", &[])?;
+        let mut bit_rate = ffmpeg::probe(&self.config, &self.file_path, "format=bit_rate", &[])?;
+        if parse_secs(&bit_rate).is_none() {
+            bit_rate = ffmpeg::probe(&self.config, &self.file_path, "stream=bit_rate", &["-select_streams", "a:0"])?;
+        }
+        match (parse_secs(&size), parse_secs(&bit_rate)) {
+            (Some(bytes), Some(bits_per_sec)) if bits_per_sec > 0.0 => {
+                Ok(Duration::from_secs_f64(bytes * 8.0 / bits_per_sec))
+            }
+            _ => Err(AudioError::InvalidParameter(format!(
+                "could not determine duration for {} (format reported '{}')",
+                self.file_path, format
+            ))),
+        }
+    }
+
+    /// Returns the duration, sample rate, channel count, codec and bitrate of the first
+    /// audio stream from a single ffprobe run. A file whose container and stream both
+    /// omit the duration gets the fallback estimate `duration` uses.
+    pub fn info(&self) -> Result<FileInfo, AudioError> {
+        let json = ffmpeg::probe_json(
+            &self.config,
+            &self.file_path,
+            &["-select_streams", "a:0", "-show_streams", "-show_format"],
+        )?;
+        let (mut info, duration) = io::parse_file_info(&json)?;
+        if duration.is_none() {
+            info.duration = self.duration()?;
+        }
+        Ok(info)
+    }
+
+    /// Returns the channel count of the first audio stream.
+    /// Probes only that field, which is cheaper than a full metadata probe.
+    pub fn channels(&self) -> Result<u8, AudioError> {
+        let value = ffmpeg::probe(&self.config, &self.file_path, "stream=channels", &["-select_streams", "a:0"])?;
+        value.parse().map_err(|_| {
+            AudioError::InvalidParameter(format!("could not parse channel count '{}' for {}", value, self.file_path))
         })
     }
 
-    /// Seeks to a given time position and outputs a new file.
-    pub fn seek(&self, position: Duration) -> Result<Self, AudioError> {
-        let output_file = format!("seeked_{}", self.file_path);
-        let pos_str = format!("{}", position.as_secs());
-        // Using "-ss" before input to perform a fast seek (copying streams)
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-ss", &pos_str, "-i", &self.file_path, "-c", "copy", &output_file, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Seeked {} seconds into {} -> {}", pos_str, self.file_path, output_file);
-            Ok(AudioProcessor { file_path: output_file })
+    /// Returns the sample rate in Hz of the first audio stream.
+    /// Probes only that field, which is cheaper than a full metadata probe.
+    pub fn sample_rate(&self) -> Result<u32, AudioError> {
+        let value = ffmpeg::probe(&self.config, &self.file_path, "stream=sample_rate", &["-select_streams", "a:0"])?;
+        value.parse().map_err(|_| {
+            AudioError::InvalidParameter(format!("could not parse sample rate '{}' for {}", value, self.file_path))
+        })
+    }
+
+    /// Returns the sample format of the first audio stream as named by FFmpeg, e.g. `s16` or `fltp`.
+    pub fn sample_format(&self) -> Result<String, AudioError> {
+        ffmpeg::probe(&self.config, &self.file_path, "stream=sample_fmt", &["-select_streams", "a:0"])
+    }
+
+    /// Measures peak, RMS and DC offset across all channels using the astats filter.
+    pub fn level_stats(&self) -> Result<LevelStats, AudioError> {
+        let stderr = ffmpeg::analyze(&self.config, &self.file_path, "astats=metadata=0", "level stats")?;
+        analysis::parse_astats(&stderr)
+    }
+
+    /// Measures the integrated loudness in LUFS (EBU R128) using the ebur128 filter.
+    pub fn integrated_loudness(&self) -> Result<f32, AudioError> {
+        let stderr = ffmpeg::analyze(&self.config, &self.file_path, "ebur128", "loudness measurement")?;
+        analysis::parse_integrated_loudness(&stderr)
+    }
+
+    /// Finds the regions quieter than `threshold_db` (dBFS) lasting at least `min_duration`,
+    /// using the silencedetect filter. Returns `(start, end)` pairs in order; a silence that
+    /// runs to the end of the file ends at its duration.
+    pub fn detect_silence(&self, threshold_db: f32, min_duration: Duration) -> Result<Vec<(Duration, Duration)>, AudioError> {
+        if !(-100.0..=0.0).contains(&threshold_db) {
+            return Err(AudioError::InvalidParameter(format!(
+                "silence threshold must be between -100 and 0 dBFS, got {}", threshold_db
+            )));
+        }
+        let filter = format!("silencedetect=noise={}dB:d={}", threshold_db, min_duration.as_secs_f64());
+        let stderr = ffmpeg::analyze(&self.config, &self.file_path, &filter, "silence detection")?;
+        analysis::parse_silence_regions(&stderr, self.duration()?)
+    }
+
+    /// Summarizes the silent regions found by `detect_silence`: where they are, how long
+    /// they add up to, and what fraction of the recording they cover.
+    pub fn silence_report(&self, threshold_db: f32, min_duration: Duration) -> Result<SilenceReport, AudioError> {
+        let regions = self.detect_silence(threshold_db, min_duration)?;
+        let total_silence: Duration = regions.iter().map(|(start, end)| end.saturating_sub(*start)).sum();
+        let duration = self.duration()?;
+        let silence_ratio = if duration.is_zero() {
+            0.0
         } else {
-            Err(AudioError::FfmpegError("ffmpeg seek failed".to_string()))
+            (total_silence.as_secs_f64() / duration.as_secs_f64()).min(1.0) as f32
+        };
+        Ok(SilenceReport { total_silence, regions, silence_ratio })
+    }
+
+    /// Returns true if the first two channels carry different audio, false for mono and
+    /// for "stereo" files whose channels are identical (dual mono) and can be downmixed
+    /// without loss. Channels within -60 dB of each other count as identical, which
+    /// absorbs dither and rounding.
+    pub fn is_true_stereo(&self) -> Result<bool, AudioError> {
+        let channels = usize::from(self.channels()?);
+        if channels < 2 {
+            return Ok(false);
+        }
+        let samples = ffmpeg::decode_f32(&self.config, &self.file_path, &[], &[])?;
+        Ok(analysis::channel_difference(&samples, channels) > db_to_linear(-60.0))
+    }
+
+    /// Returns the correlation of the first two channels, -1.0 to 1.0: near 1.0 for
+    /// near-mono material, near 0.0 for unrelated channels and negative when one channel
+    /// is polarity-inverted. Mono and silent files return 0.0.
+    pub fn phase_correlation(&self) -> Result<f32, AudioError> {
+        let channels = usize::from(self.channels()?);
+        if channels < 2 {
+            return Ok(0.0);
+        }
+        let samples = ffmpeg::decode_f32(&self.config, &self.file_path, &[], &[])?;
+        Ok(analysis::channel_correlation(&samples, channels))
+    }
+
+    /// Checks the first two channels for a polarity mismatch, as from a miswired mic, and
+    /// inverts the second channel if they are anti-correlated (correlation below
+    /// `PHASE_INVERSION_THRESHOLD`). Returns the fixed file, or a copy of this processor
+    /// when no fix was needed, and whether the fix was applied. Mono input is left alone.
+    pub fn fix_phase(&self) -> Result<(Self, bool), AudioError> {
+        self.fix_phase_to(&self.prefixed_output("phase_fixed"))
+    }
+
+    /// Like `fix_phase`, but writes the fixed file to `output_path`. Nothing is written
+    /// when no fix was needed.
+    pub fn fix_phase_to(&self, output_path: &str) -> Result<(Self, bool), AudioError> {
+        let channels = usize::from(self.channels()?);
+        if channels < 2 {
+            return Ok((self.clone(), false));
+        }
+        let correlation = self.phase_correlation()?;
+        if correlation >= PHASE_INVERSION_THRESHOLD {
+            println!("Channels of {} are in phase (correlation {:.2}), leaving unchanged", self.file_path, correlation);
+            return Ok((self.clone(), false));
+        }
+        // Every channel is mapped through so layouts wider than stereo survive.
+        let mut filter = format!("pan={}c", channels);
+        for channel in 0..channels {
+            let gain = if channel == 1 { "-1*" } else { "" };
+            filter.push_str(&format!("|c{}={}c{}", channel, gain, channel));
+        }
+        let output_file = output_path.to_string();
+        self.run_ffmpeg(&[], &["-af", &filter, &output_file], "fix phase")?;
+        println!("Inverted channel 2 of {} (correlation {:.2}) -> {}", self.file_path, correlation, output_file);
+        Ok((self.derived(output_file), true))
+    }
+
+    /// Returns waveform peaks for drawing: the mono mixdown is cut into buckets of
+    /// `samples_per_pixel` samples and each contributes its minimum and maximum, in
+    /// -1.0..1.0, as `[min0, max0, min1, max1, ...]`. The last bucket may be shorter.
+    /// The decoded audio is streamed from ffmpeg rather than held in memory.
+    pub fn waveform_peaks(&self, samples_per_pixel: u32) -> Result<Vec<f32>, AudioError> {
+        if samples_per_pixel == 0 {
+            return Err(AudioError::InvalidParameter("samples_per_pixel must be at least 1".to_string()));
+        }
+        let mut peaks = Vec::new();
+        let (mut min, mut max, mut count) = (0.0f32, 0.0f32, 0u32);
+        let mut carry = None;
+        ffmpeg::stream_pcm(&self.config, &self.file_path, "s16le", &["-ac", "1"], |chunk| {
+            let mut bytes = chunk.iter().copied();
+            // A sample can straddle two chunks; its first byte waits in `carry`.
+            while let Some(low) = carry.take().or_else(|| bytes.next()) {
+                let Some(high) = bytes.next() else {
+                    carry = Some(low);
+                    break;
+                };
+                let sample = f32::from(i16::from_le_bytes([low, high])) / 32768.0;
+                (min, max) = if count == 0 { (sample, sample) } else { (min.min(sample), max.max(sample)) };
+                count += 1;
+                if count == samples_per_pixel {
+                    peaks.extend([min, max]);
+                    count = 0;
+                }
+            }
+        })?;
+        if count > 0 {
+            peaks.extend([min, max]);
         }
+        Ok(peaks)
+    }
+
+    /// Decodes only the window from `start` to `end` (seeking with `-ss`/`-to` before the
+    /// input, so the rest of the file isn't decoded) to the mono mixdown as f32 samples,
+    /// and returns them with the sample rate. The range is checked as for `trim`.
+    pub fn read_samples_range(&self, start: Duration, end: Duration) -> Result<(Vec<f32>, u32), AudioError> {
+        let (start, end) = self.trim_bounds(start, end)?;
+        let sample_rate = self.sample_rate()?;
+        let (start_str, end_str) = (start.as_secs_f64().to_string(), end.as_secs_f64().to_string());
+        let samples = ffmpeg::decode_f32(&self.config, &self.file_path, &["-ss", &start_str, "-to", &end_str], &["-ac", "1"])?;
+        Ok((samples, sample_rate))
+    }
+
+    /// Returns an approximation of the TT DR meter's dynamic range value, in dB.
+    /// Computed from decoded PCM per channel and averaged; heavily compressed masters
+    /// score low, dynamic recordings high. Not a certified DR measurement.
+    pub fn dynamic_range(&self) -> Result<f32, AudioError> {
+        let channels = usize::from(self.channels()?);
+        let sample_rate = self.sample_rate()?;
+        let samples = ffmpeg::decode_f32(&self.config, &self.file_path, &[], &[])?;
+        Ok(analysis::dynamic_range_db(&samples, channels, sample_rate))
+    }
+
+    /// Seeks to a given time position and outputs a new file.
+    pub fn seek(&self, position: Duration) -> Result<Self, AudioError> {
+        self.seek_to(position, &self.prefixed_output("seeked"))
+    }
+
+    /// Like `seek`, but writes the output to `output_path`.
+    pub fn seek_to(&self, position: Duration, output_path: &str) -> Result<Self, AudioError> {
+        let output_file = output_path.to_string();
+        let pos_str = format!("{}", position.as_secs_f64());
+        self.run_ffmpeg(&["-ss", &pos_str], &stream_copy_args(&output_file), "seek")?;
+        println!("Seeked {} seconds into {} -> {}", pos_str, self.file_path, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Adds `length` of silence before the audio, e.g. as a lead-in to sync with a countdown.
+    pub fn prepend_silence(&self, length: Duration) -> Result<Self, AudioError> {
+        self.prepend_silence_to(length, &self.prefixed_output("lead_in"))
+    }
+
+    /// Like `prepend_silence`, but writes the output to `output_path`. Every channel is
+    /// delayed with `adelay`, which fills the gap with silence in the source's own format.
+    pub fn prepend_silence_to(&self, length: Duration, output_path: &str) -> Result<Self, AudioError> {
+        let output_file = output_path.to_string();
+        let filter = format!("adelay=delays={}:all=1", length.as_millis());
+        self.run_ffmpeg(&[], &["-af", &filter, &output_file], "prepend silence")?;
+        println!("Prepended {:?} of silence to {} -> {}", length, self.file_path, output_file);
+        Ok(self.derived(output_file))
     }
 
     /// Trims the audio between start and end durations.
     /// Returns a new AudioProcessor instance with the trimmed segment.
     pub fn trim(&self, start: Duration, end: Duration) -> Result<Self, AudioError> {
-        let output_file = format!("trimmed_{}", self.file_path);
-        let start_str = format!("{}", start.as_secs());
-        let end_str = format!("{}", end.as_secs());
-        // "-ss" before input and "-to" after input for trimming without re-encoding.
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-ss", &start_str, "-to", &end_str, "-i", &self.file_path, "-c", "copy", &output_file, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Trimmed {} from {} to {} seconds -> {}", self.file_path, start_str, end_str, output_file);
-            Ok(AudioProcessor { file_path: output_file })
-        } else {
-            Err(AudioError::FfmpegError("ffmpeg trim failed".to_string()))
+        self.trim_to(start, end, &self.prefixed_output("trimmed"))
+    }
+
+    /// Like `trim`, but writes the output to `output_path`.
+    /// Fails with `InvalidParameter` if `start` is not before `end` or not before the end
+    /// of the file; an `end` past the end of the file is clamped to it.
+    pub fn trim_to(&self, start: Duration, end: Duration, output_path: &str) -> Result<Self, AudioError> {
+        let (start, end) = self.trim_bounds(start, end)?;
+        let output_file = output_path.to_string();
+        let (start_str, end_str) = (format!("{}", start.as_secs_f64()), format!("{}", end.as_secs_f64()));
+        self.run_ffmpeg(&trim_input_opts(&start_str, &end_str), &stream_copy_args(&output_file), "trim")?;
+        println!("Trimmed {} from {} to {} seconds -> {}", self.file_path, start_str, end_str, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Checks `trim`'s range against the file's duration, returning it with `end` clamped
+    /// to the duration.
+    fn trim_bounds(&self, start: Duration, end: Duration) -> Result<(Duration, Duration), AudioError> {
+        if start >= end {
+            return Err(AudioError::InvalidParameter(format!("trim start {:?} must be before end {:?}", start, end)));
+        }
+        let total = self.duration()?;
+        if start >= total {
+            return Err(AudioError::InvalidParameter(format!(
+                "trim start {:?} is past the end of {} ({:?})", start, self.file_path, total
+            )));
         }
+        if end > total {
+            log::warn!("trim end {:?} is past the end of {} ({:?}); clamping", end, self.file_path, total);
+        }
+        Ok((start, end.min(total)))
+    }
+
+    /// Trims like `trim`, but moves each cut to the nearest zero crossing (of the mono
+    /// mixdown) within 1 ms so the cut doesn't click. The adjustment is at most a
+    /// millisecond either way; a cut with no crossing that close is left where it is.
+    /// Unlike `trim`, the cut is sample-accurate and so re-encodes. The range is checked
+    /// and clamped as for `trim`.
+    pub fn trim_zero_crossing(&self, start: Duration, end: Duration) -> Result<Self, AudioError> {
+        self.trim_zero_crossing_to(start, end, &self.prefixed_output("trimmed"))
+    }
+
+    /// Like `trim_zero_crossing`, but writes the output to `output_path`.
+    pub fn trim_zero_crossing_to(&self, start: Duration, end: Duration, output_path: &str) -> Result<Self, AudioError> {
+        let (start, end) = self.trim_bounds(start, end)?;
+        let sample_rate = self.sample_rate()?;
+        let window = u64::from(sample_rate / 1000).max(1);
+        let snap = |time: Duration| -> Result<u64, AudioError> {
+            let target = (time.as_secs_f64() * f64::from(sample_rate)).round() as u64;
+            let first = target.saturating_sub(window);
+            let around = format!("atrim=start_sample={}:end_sample={}", first, target + window + 1);
+            let samples = ffmpeg::decode_f32(&self.config, &self.file_path, &[], &["-af", &around, "-ac", "1"])?;
+            let crossing = analysis::nearest_zero_crossing(&samples, (target - first) as usize, window as usize);
+            Ok(crossing.map_or(target, |index| first + index as u64))
+        };
+        let (start_sample, end_sample) = (snap(start)?, snap(end)?);
+        let filter = format!("atrim=start_sample={}:end_sample={},asetpts=PTS-STARTPTS", start_sample, end_sample);
+        self.run_ffmpeg(&[], &["-af", &filter, output_path], "trim at zero crossings")?;
+        println!(
+            "Trimmed {} at zero crossings from sample {} to {} -> {}",
+            self.file_path, start_sample, end_sample, output_path
+        );
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Trims from `start` to `trim_off_end` before the end of the file,
+    /// e.g. `trim_from_end(1s, 2s)` keeps everything from 1s until 2s before the end.
+    pub fn trim_from_end(&self, start: Duration, trim_off_end: Duration) -> Result<Self, AudioError> {
+        let total = self.duration()?;
+        let end = total.checked_sub(trim_off_end).ok_or_else(|| {
+            AudioError::InvalidParameter(format!(
+                "cannot trim {:?} off the end of a {:?} clip", trim_off_end, total
+            ))
+        })?;
+        if end <= start {
+            return Err(AudioError::InvalidParameter(format!(
+                "trim end {:?} (from end offset {:?}) must be after start {:?}", end, trim_off_end, start
+            )));
+        }
+        self.trim(start, end)
+    }
+
+    /// Trims leading and trailing silence quieter than `threshold_db` (dBFS), leaving up to
+    /// `keep_padding` of it at each end so the cut doesn't sound abrupt.
+    pub fn trim_silence_edges(&self, threshold_db: f32, keep_padding: Duration) -> Result<Self, AudioError> {
+        self.trim_silence_edges_to(threshold_db, keep_padding, &self.prefixed_output("silence_trimmed"))
+    }
+
+    /// Like `trim_silence_edges`, but writes the output to `output_path`.
+    pub fn trim_silence_edges_to(&self, threshold_db: f32, keep_padding: Duration, output_path: &str) -> Result<Self, AudioError> {
+        if !(-100.0..=0.0).contains(&threshold_db) {
+            return Err(AudioError::InvalidParameter(format!(
+                "silence threshold must be between -100 and 0 dBFS, got {}", threshold_db
+            )));
+        }
+        // silenceremove only trims leading silence reliably, so the end is trimmed on the
+        // reversed signal and flipped back.
+        let trim_start = format!(
+            "silenceremove=start_periods=1:start_threshold={}dB:start_silence={}",
+            threshold_db,
+            keep_padding.as_secs_f64()
+        );
+        let filter = format!("{0},areverse,{0},areverse", trim_start);
+        let output_file = output_path.to_string();
+        self.run_ffmpeg(&[], &["-af", &filter, &output_file], "trim silence edges")?;
+        println!(
+            "Trimmed edge silence below {} dB from {} keeping {:?} -> {}",
+            threshold_db, self.file_path, keep_padding, output_file
+        );
+        Ok(self.derived(output_file))
+    }
+
+    /// Cuts leading and trailing silence quieter than `threshold_db` (dBFS, at most 0),
+    /// but only stretches of at least `min_silence`, so short pauses at the edges stay.
+    /// The edges are found with `detect_silence`; a file that is silent throughout fails
+    /// with `InvalidParameter`.
+    pub fn trim_silence(&self, threshold_db: f32, min_silence: Duration) -> Result<Self, AudioError> {
+        self.trim_silence_to(threshold_db, min_silence, &self.prefixed_output("silence_trimmed"))
+    }
+
+    /// Like `trim_silence`, but writes the output to `output_path`.
+    pub fn trim_silence_to(&self, threshold_db: f32, min_silence: Duration, output_path: &str) -> Result<Self, AudioError> {
+        if threshold_db > 0.0 {
+            return Err(AudioError::InvalidParameter(format!(
+                "silence threshold must be at most 0 dBFS, got {}", threshold_db
+            )));
+        }
+        // silencedetect reports edge regions a few milliseconds off the exact ends.
+        let tolerance = Duration::from_millis(10);
+        let total = self.duration()?;
+        let regions = self.detect_silence(threshold_db, min_silence)?;
+        let start = match regions.first() {
+            Some(&(start, end)) if start <= tolerance => end,
+            _ => Duration::ZERO,
+        };
+        let end = match regions.last() {
+            Some(&(start, end)) if end + tolerance >= total => start,
+            _ => total,
+        };
+        if end <= start {
+            return Err(AudioError::InvalidParameter(format!(
+                "{} is silent below {} dB throughout", self.file_path, threshold_db
+            )));
+        }
+        let filter = format!("atrim=start={}:end={},asetpts=PTS-STARTPTS", start.as_secs_f64(), end.as_secs_f64());
+        self.run_ffmpeg(&[], &["-af", &filter, output_path], "trim silence")?;
+        println!(
+            "Trimmed silence below {} dB from {}, keeping {:?} to {:?} -> {}",
+            threshold_db, self.file_path, start, end, output_path
+        );
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Returns true if the configured ffmpeg can encode with `encoder` (e.g. `libopus`).
+    /// The encoder list is read once per ffmpeg binary and cached.
+    pub fn has_encoder(&self, encoder: &str) -> Result<bool, AudioError> {
+        Ok(ffmpeg::encoders(&self.config)?.contains(encoder))
     }
 
     /// Transcodes the current audio to a different format.
+    /// `output_path` must carry the format's extension (see `AudioFormat::extension`); one
+    /// without an extension gets it appended, and a different one fails with
+    /// `InvalidParameter`. Fails with `UnsupportedFormat` before running if ffmpeg lacks
+    /// the format's encoder.
     pub fn transcode(&self, output_format: AudioFormat, output_path: &str) -> Result<(), AudioError> {
-        // Let FFmpeg decide the codec based on output extension.
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-i", &self.file_path, output_path, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Transcoded {} to format {:?} -> {}", self.file_path, output_format, output_path);
-            Ok(())
+        let output_path = output_format.output_path(output_path)?;
+        ffmpeg::require_encoder(&self.config, output_format.default_codec())?;
+        // The extension now matches the format, so FFmpeg picks the muxer and codec from it.
+        println!("Transcoding {} to format {:?}", self.file_path, output_format);
+        self.transcode_with(&output_path, TranscodeOptions::default())
+    }
+
+    /// Transcodes to `output_format` and returns the encoded bytes, read from ffmpeg's
+    /// stdout (`pipe:1`) instead of written to a file. Formats whose muxer seeks back to
+    /// finish its header (WAV's sizes, FLAC's stream info) leave those fields unset.
+    pub fn transcode_to_bytes(&self, output_format: AudioFormat) -> Result<Vec<u8>, AudioError> {
+        ffmpeg::require_encoder(&self.config, output_format.default_codec())?;
+        let args = self.ffmpeg_args(&[], &["-f", output_format.format_name(), "pipe:1"]);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let bytes = ffmpeg::run_to_stdout(&self.config, &args, self.pipe_input.as_ref(), "transcode to bytes")?;
+        println!("Transcoded {} to {} bytes of {:?}", self.file_path, bytes.len(), output_format);
+        Ok(bytes)
+    }
+
+    /// Transcodes like `transcode`, but in the background, returning an iterator over the
+    /// fraction of the input encoded so far. The last item is `1.0` once the output is
+    /// complete, or the error that stopped the transcode. `output_path` and the encoder are
+    /// checked as for `transcode`, and a failed check is the only item. If the input's
+    /// duration can't be probed, only the final `1.0` is reported.
+    pub fn transcode_progress_iter(&self, output_format: AudioFormat, output_path: &str) -> impl Iterator<Item = Result<f32, AudioError>> {
+        let checked = output_format
+            .output_path(output_path)
+            .and_then(|path| ffmpeg::require_encoder(&self.config, output_format.default_codec()).map(|()| path));
+        let output_path = match checked {
+            Ok(path) => path,
+            Err(e) => return ffmpeg::failed_progress(e, "transcode"),
+        };
+        let total = self.duration().unwrap_or_default();
+        let args = self.ffmpeg_args(&[], &["-f", output_format.format_name(), &output_path]);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        ffmpeg::run_with_progress(&self.config, &args, self.pipe_input.as_ref(), total, "transcode")
+    }
+
+    /// Transcodes to `output_path`, picking the format from its extension, and calls
+    /// `on_progress` with the fraction of the input encoded so far (0.0 to 1.0), never
+    /// decreasing. `1.0` is reported once, after ffmpeg succeeds. If the input's duration
+    /// can't be probed, only that final `1.0` is reported. Before ffmpeg starts, a path
+    /// without an extension fails with `InvalidParameter`, and one with an `AudioFormat`'s
+    /// extension fails with `UnsupportedFormat` if ffmpeg lacks that format's encoder.
+    pub fn transcode_with_progress(&self, output_path: &str, mut on_progress: impl FnMut(f32)) -> Result<(), AudioError> {
+        if std::path::Path::new(output_path).extension().is_none() {
+            return Err(AudioError::InvalidParameter(format!(
+                "output path {} has no extension to pick the format from", output_path
+            )));
+        }
+        if let Some(format) = AudioFormat::for_path(output_path) {
+            ffmpeg::require_encoder(&self.config, format.default_codec())?;
+        }
+        let total = self.duration().unwrap_or_default();
+        let args = self.ffmpeg_args(&[], &[output_path]);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        for fraction in ffmpeg::run_with_progress(&self.config, &args, self.pipe_input.as_ref(), total, "transcode") {
+            on_progress(fraction?);
+        }
+        println!("Transcoded {} -> {}", self.file_path, output_path);
+        Ok(())
+    }
+
+    /// Starts transcoding like `transcode` and returns immediately with a handle to wait
+    /// on or cancel the run. `output_path` is checked against the format as for `transcode`.
+    pub fn transcode_spawn(&self, output_format: AudioFormat, output_path: &str) -> Result<TranscodeHandle, AudioError> {
+        let output_path = output_format.output_path(output_path)?;
+        ffmpeg::require_encoder(&self.config, output_format.default_codec())?;
+        let args = self.ffmpeg_args(&[], &["-f", output_format.format_name(), &output_path]);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let job = ffmpeg::start(&self.config, &args, self.pipe_input.as_ref(), "transcode")?;
+        println!("Started transcoding {} to format {:?} -> {}", self.file_path, output_format, output_path);
+        Ok(TranscodeHandle::new(job))
+    }
+
+    /// Transcodes to `output_path` with explicit encoder settings; see `TranscodeOptions`.
+    /// An explicit codec that the output container can't hold fails with `InvalidParameter`;
+    /// it is then checked with `has_encoder`, failing with `UnsupportedFormat`.
+    pub fn transcode_with(&self, output_path: &str, options: TranscodeOptions) -> Result<(), AudioError> {
+        options.check_codec(output_path)?;
+        if let Some(codec) = &options.codec {
+            ffmpeg::require_encoder(&self.config, codec.as_ffmpeg_arg())?;
+        }
+        let options_args = options.to_args();
+        let mut args: Vec<&str> = options_args.iter().map(String::as_str).collect();
+        args.push(output_path);
+        self.run_ffmpeg(&[], &args, "transcode")?;
+        println!("Transcoded {} with {:?} -> {}", self.file_path, options, output_path);
+        Ok(())
+    }
+
+    /// The same as `transcode_with`, with the arguments in options-first order.
+    #[deprecated(note = "use `transcode_with`, which takes the output path first")]
+    pub fn transcode_with_options(&self, opts: TranscodeOptions, output_path: &str) -> Result<(), AudioError> {
+        self.transcode_with(output_path, opts)
+    }
+
+    /// Transcodes to the format best suited to `quality`, appending the matching extension
+    /// to `output_path_stem`, and returns the path written.
+    pub fn transcode_for_quality(&self, quality: QualityTarget, output_path_stem: &str) -> Result<String, AudioError> {
+        let output_path = format!("{}.{}", output_path_stem, quality.extension());
+        self.transcode_with(&output_path, quality.options())?;
+        Ok(output_path)
+    }
+
+    /// Transcodes to a lossy format at the highest bitrate that keeps the file under `max_bytes`.
+    /// The bitrate is derived from `max_bytes / duration`, less a 10% margin for container
+    /// overhead, and clamped to the format's valid range. Fails with `InvalidParameter` for
+    /// lossless formats, or when even the format's minimum bitrate would exceed the budget.
+    /// `output_path` is checked against the format as for `transcode`.
+    pub fn transcode_to_max_size(&self, output_format: AudioFormat, max_bytes: u64, output_path: &str) -> Result<(), AudioError> {
+        let (min_kbps, _) = output_format.bitrate_range().ok_or_else(|| {
+            AudioError::InvalidParameter(format!("{:?} is lossless; its size can't be targeted by bitrate", output_format))
+        })?;
+        let output_path = output_format.output_path(output_path)?;
+        ffmpeg::require_encoder(&self.config, output_format.default_codec())?;
+        let secs = self.duration()?.as_secs_f64();
+        if secs <= 0.0 {
+            return Err(AudioError::InvalidParameter(format!("{} has no duration", self.file_path)));
+        }
+        let budget_kbps = (max_bytes as f64 * 8.0 * 0.9 / secs / 1000.0).floor() as u32;
+        let kbps = output_format.bitrate_at_most(budget_kbps).ok_or_else(|| {
+            AudioError::InvalidParameter(format!(
+                "{} bytes is too small for {:.1}s of {:?}; the minimum bitrate is {} kbps",
+                max_bytes, secs, output_format, min_kbps
+            ))
+        })?;
+        let bitrate = format!("{}k", kbps);
+        self.run_ffmpeg(&[], &["-b:a", &bitrate, "-f", output_format.format_name(), &output_path], "transcode to max size")?;
+        println!(
+            "Transcoded {} to format {:?} at {} to fit {} bytes -> {}",
+            self.file_path, output_format, bitrate, max_bytes, output_path
+        );
+        Ok(())
+    }
+
+    /// Writes several outputs from a single decode pass.
+    /// When every target has the same format the stream is encoded once and fanned out with
+    /// FFmpeg's tee muxer (`[f=mp3]a.mp3|[f=mp3]b.mp3`). The tee muxer can't give each target
+    /// its own encoder, so mixed formats are written as multiple outputs of one ffmpeg run,
+    /// which still decodes the input only once. With `atomic_output`, each of those outputs
+    /// is staged separately; the tee muxer's outputs are written in place.
+    pub fn tee(&self, outputs: &[(AudioFormat, String)]) -> Result<(), AudioError> {
+        let (first_format, _) = outputs.first().ok_or_else(|| {
+            AudioError::InvalidParameter("tee needs at least one output".to_string())
+        })?;
+        for (format, _) in outputs {
+            ffmpeg::require_encoder(&self.config, format.default_codec())?;
+        }
+        let mut owned_args: Vec<String> = Vec::new();
+        if outputs.iter().all(|(format, _)| format == first_format) {
+            let spec = outputs
+                .iter()
+                .map(|(format, path)| format!("[f={}]{}", format.format_name(), escape_tee_path(path)))
+                .collect::<Vec<_>>()
+                .join("|");
+            owned_args.extend(["-map", "0:a", "-c:a", first_format.default_codec(), "-f", "tee"].map(String::from));
+            owned_args.push(spec);
         } else {
-            Err(AudioError::FfmpegError("ffmpeg transcode failed".to_string()))
+            for (format, path) in outputs {
+                owned_args.extend(["-map", "0:a", "-c:a", format.default_codec(), "-f", format.format_name()].map(String::from));
+                owned_args.push(path.clone());
+            }
+        }
+        let args: Vec<&str> = owned_args.iter().map(String::as_str).collect();
+        self.run_ffmpeg(&[], &args, "tee")?;
+        println!("Wrote {} outputs from {} in one pass", outputs.len(), self.file_path);
+        Ok(())
+    }
+
+    /// Packages the audio as an HLS stream for web delivery.
+    /// Writes `playlist.m3u8` plus `.ts` segments into `output_dir` (created if missing)
+    /// and returns the playlist path.
+    pub fn to_hls(&self, segment_duration: Duration, output_dir: &str) -> Result<String, AudioError> {
+        if segment_duration.is_zero() {
+            return Err(AudioError::InvalidParameter("HLS segment duration must be greater than zero".to_string()));
+        }
+        let dir = std::path::Path::new(output_dir);
+        let resolved_dir = self.config.resolve_path(dir);
+        if resolved_dir.exists() && !resolved_dir.is_dir() {
+            return Err(AudioError::InvalidParameter(format!("HLS output path {} is not a directory", output_dir)));
+        }
+        std::fs::create_dir_all(&resolved_dir).map_err(AudioError::IoError)?;
+
+        let playlist = dir.join("playlist.m3u8").to_string_lossy().into_owned();
+        let segment_pattern = dir.join("segment_%03d.ts").to_string_lossy().into_owned();
+        let segment_secs = format!("{}", segment_duration.as_secs_f64());
+        self.run_ffmpeg(
+            &[],
+            &[
+                "-c:a", "aac",
+                "-f", "hls",
+                "-hls_time", &segment_secs,
+                "-hls_playlist_type", "vod",
+                "-hls_segment_filename", &segment_pattern,
+                &playlist,
+            ],
+            "hls packaging",
+        )?;
+        println!("Packaged {} as HLS with {}s segments -> {}", self.file_path, segment_secs, playlist);
+        Ok(playlist)
+    }
+
+    /// Converts to the 16 kHz mono 16-bit PCM WAV most speech-recognition models expect.
+    pub fn to_asr_wav(&self, output_path: &str) -> Result<Self, AudioError> {
+        self.run_ffmpeg(
+            &[],
+            &["-ar", "16000", "-ac", "1", "-c:a", "pcm_s16le", "-f", "wav", output_path],
+            "asr wav conversion",
+        )?;
+        println!("Converted {} to 16 kHz mono WAV -> {}", self.file_path, output_path);
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Resamples the audio to `sample_rate` Hz (8000 to 192000), writing
+    /// `resampled_<rate>_<name>` next to the input.
+    pub fn resample(&self, sample_rate: u32) -> Result<Self, AudioError> {
+        self.resample_to(sample_rate, &self.prefixed_output(&format!("resampled_{}", sample_rate)))
+    }
+
+    /// Like `resample`, but writes the output to `output_path`.
+    pub fn resample_to(&self, sample_rate: u32, output_path: &str) -> Result<Self, AudioError> {
+        check_sample_rate(sample_rate)?;
+        let rate = sample_rate.to_string();
+        self.run_ffmpeg(&[], &["-ar", &rate, output_path], "resample")?;
+        println!("Resampled {} to {} Hz -> {}", self.file_path, sample_rate, output_path);
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Changes the channel count with `-ac`: stereo to mono downmixes, mono to stereo
+    /// copies the channel to both sides. Writes `<n>ch_<name>` next to the input.
+    pub fn set_channels(&self, channels: u16) -> Result<Self, AudioError> {
+        self.set_channels_to(channels, &self.prefixed_output(&format!("{}ch", channels)))
+    }
+
+    /// Like `set_channels`, but writes the output to `output_path`.
+    pub fn set_channels_to(&self, channels: u16, output_path: &str) -> Result<Self, AudioError> {
+        if channels == 0 {
+            return Err(AudioError::InvalidParameter("channel count must be at least 1".to_string()));
         }
+        let count = channels.to_string();
+        self.run_ffmpeg(&[], &["-ac", &count, output_path], "set channels")?;
+        println!("Converted {} to {} channels -> {}", self.file_path, channels, output_path);
+        Ok(self.derived(output_path.to_string()))
     }
 
     /// Adjusts the audio volume by a scaling factor.
+    /// Boosts that would push the peak above 0 dBFS are handled per the config's
+    /// `clipping_policy`. Negative factors fail with `InvalidParameter`; 0.0 mutes.
     pub fn adjust_volume(&self, factor: f32) -> Result<Self, AudioError> {
-        let output_file = format!("volume_adjusted_{}", self.file_path);
-        let filter = format!("volume={}", factor);
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-i", &self.file_path, "-af", &filter, &output_file, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Adjusted volume of {} by factor {} -> {}", self.file_path, factor, output_file);
-            Ok(AudioProcessor { file_path: output_file })
-        } else {
-            Err(AudioError::FfmpegError("ffmpeg adjust volume failed".to_string()))
+        self.adjust_volume_to(factor, &self.prefixed_output("volume_adjusted"))
+    }
+
+    /// Like `adjust_volume`, but writes the output to `output_path`.
+    pub fn adjust_volume_to(&self, factor: f32, output_path: &str) -> Result<Self, AudioError> {
+        let output_file = output_path.to_string();
+        let mut filter = volume_filter(factor)?;
+        if factor > 1.0 {
+            self.guard_clipping(&mut filter, linear_to_db(factor), &format!("volume factor {}", factor))?;
+        }
+        self.run_ffmpeg(&[], &["-af", &filter, &output_file], "adjust volume")?;
+        println!("Adjusted volume of {} by factor {} -> {}", self.file_path, factor, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Adjusts the audio volume by `gain_db` decibels, e.g. `6.0` to roughly double the
+    /// amplitude. Gains outside -96..=48 dB fail with `InvalidParameter`; boosts that would
+    /// clip are handled per the config's `clipping_policy`, as for `adjust_volume`.
+    pub fn adjust_volume_db(&self, gain_db: f32) -> Result<Self, AudioError> {
+        self.adjust_volume_db_to(gain_db, &self.prefixed_output("volume_adjusted"))
+    }
+
+    /// Like `adjust_volume_db`, but writes the output to `output_path`.
+    pub fn adjust_volume_db_to(&self, gain_db: f32, output_path: &str) -> Result<Self, AudioError> {
+        let output_file = output_path.to_string();
+        let mut filter = volume_db_filter(gain_db)?;
+        if gain_db > 0.0 {
+            self.guard_clipping(&mut filter, gain_db, &format!("volume gain {} dB", gain_db))?;
+        }
+        self.run_ffmpeg(&[], &["-af", &filter, &output_file], "adjust volume")?;
+        println!("Adjusted volume of {} by {} dB -> {}", self.file_path, gain_db, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Applies the config's `clipping_policy` to a boost of `gain_db`: if it would push the
+    /// peak above 0 dBFS, fails with `InvalidParameter` or appends a limiter to `filter`.
+    fn guard_clipping(&self, filter: &mut String, gain_db: f32, gain: &str) -> Result<(), AudioError> {
+        if self.config.clipping_policy == ClippingPolicy::Allow {
+            return Ok(());
+        }
+        let peak_db = self.level_stats()?.peak_db + gain_db;
+        if peak_db > 0.0 {
+            if self.config.clipping_policy == ClippingPolicy::Reject {
+                return Err(AudioError::InvalidParameter(format!(
+                    "{} would clip {} (peak {:.1} dBFS)", gain, self.file_path, peak_db
+                )));
+            }
+            log::warn!("{} would peak at {:.1} dBFS; limiting", gain, peak_db);
+            // The limiter can overshoot its ceiling slightly, so aim a little below full scale.
+            filter.push_str(&format!(",alimiter=limit={}", db_to_linear(-0.5)));
+        }
+        Ok(())
+    }
+
+    /// Applies a caller-supplied `-af` filter chain, e.g. `highpass=f=80,volume=0.8`.
+    /// The chain is validated first, so a malformed one fails with ffmpeg's explanation
+    /// before the input is decoded.
+    pub fn apply_raw_filter(&self, filter: &str) -> Result<Self, AudioError> {
+        self.apply_raw_filter_to(filter, &self.prefixed_output("filtered"))
+    }
+
+    /// Like `apply_raw_filter`, but writes the output to `output_path`.
+    pub fn apply_raw_filter_to(&self, filter: &str, output_path: &str) -> Result<Self, AudioError> {
+        ffmpeg::validate_filtergraph(&self.config, filter, false)?;
+        let output_file = output_path.to_string();
+        self.run_ffmpeg(&[], &["-af", filter, &output_file], "raw filter")?;
+        println!("Applied filter '{}' to {} -> {}", filter, self.file_path, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Applies a caller-supplied `-filter_complex` graph with this processor's audio as
+    /// input `[0:a]`; its unlabelled output is written. The graph is validated first,
+    /// like `apply_raw_filter`.
+    pub fn apply_filter_complex(&self, graph: &str) -> Result<Self, AudioError> {
+        self.apply_filter_complex_to(graph, &self.prefixed_output("filtered"))
+    }
+
+    /// Like `apply_filter_complex`, but writes the output to `output_path`.
+    pub fn apply_filter_complex_to(&self, graph: &str, output_path: &str) -> Result<Self, AudioError> {
+        ffmpeg::validate_filtergraph(&self.config, graph, true)?;
+        let output_file = output_path.to_string();
+        self.run_ffmpeg(&[], &["-filter_complex", graph, &output_file], "filter complex")?;
+        println!("Applied filter graph '{}' to {} -> {}", graph, self.file_path, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Applies gain automation read from a CSV file of `time_seconds,gain_db` rows.
+    /// Gain is interpolated linearly (in dB) between rows and held before the first and
+    /// after the last row. Rows must be sorted by time and fall within the clip.
+    pub fn apply_envelope_file(&self, csv_path: &str) -> Result<Self, AudioError> {
+        self.apply_envelope_file_to(csv_path, &self.prefixed_output("enveloped"))
+    }
+
+    /// Like `apply_envelope_file`, but writes the output to `output_path`.
+    pub fn apply_envelope_file_to(&self, csv_path: &str, output_path: &str) -> Result<Self, AudioError> {
+        let contents = std::fs::read_to_string(self.config.resolve_path(csv_path)).map_err(AudioError::IoError)?;
+        let points = parse_envelope(&contents)?;
+        let total = self.duration()?.as_secs_f64();
+        if let Some(&(time, _)) = points.iter().find(|(time, _)| *time > total) {
+            return Err(AudioError::InvalidParameter(format!(
+                "envelope time {}s is beyond the end of the clip ({}s)", time, total
+            )));
         }
+        let output_file = output_path.to_string();
+        let filter = envelope_to_filter(&points);
+        self.run_ffmpeg(&[], &["-af", &filter, &output_file], "apply envelope")?;
+        println!("Applied {}-point envelope from {} on {} -> {}", points.len(), csv_path, self.file_path, output_file);
+        Ok(self.derived(output_file))
     }
 
     /// Changes the playback speed (and optionally pitch) by a factor.
+    /// Factors outside the config's `[min_speed, max_speed]` range are clamped or rejected
+    /// depending on `clamp_speed`; expect audible artifacts near either extreme.
     pub fn change_speed(&self, factor: f32) -> Result<Self, AudioError> {
-        let output_file = format!("speed_changed_{}", self.file_path);
-        // atempo filter supports 0.5 to 2.0; for other values, chain multiple filters.
-        let filter = format!("atempo={}", factor);
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-i", &self.file_path, "-filter:a", &filter, &output_file, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Changed speed of {} by factor {} -> {}", self.file_path, factor, output_file);
-            Ok(AudioProcessor { file_path: output_file })
-        } else {
-            Err(AudioError::FfmpegError("ffmpeg change speed failed".to_string()))
-        }
+        self.change_speed_to(factor, &self.prefixed_output("speed_changed"))
+    }
+
+    /// Like `change_speed`, but writes the output to `output_path`.
+    /// The output keeps the input's sample rate.
+    pub fn change_speed_to(&self, factor: f32, output_path: &str) -> Result<Self, AudioError> {
+        // Check the factor before probing, so a bad one fails without running ffprobe.
+        let factor = self.config.speed_factor(factor)?;
+        self.render_speed(factor, self.sample_rate()?, output_path)
+    }
+
+    /// Changes speed like `change_speed`, writing the output at `sample_rate` (8000 to
+    /// 192000 Hz) instead of the input's rate.
+    pub fn change_speed_at_rate(&self, factor: f32, sample_rate: u32) -> Result<Self, AudioError> {
+        self.change_speed_at_rate_to(factor, sample_rate, &self.prefixed_output("speed_changed"))
+    }
+
+    /// Like `change_speed_at_rate`, but writes the output to `output_path`.
+    pub fn change_speed_at_rate_to(&self, factor: f32, sample_rate: u32, output_path: &str) -> Result<Self, AudioError> {
+        let factor = self.config.speed_factor(factor)?;
+        self.render_speed(factor, sample_rate, output_path)
+    }
+
+    /// Renders a speed change by `factor`, already checked (and clamped) by `speed_factor`.
+    fn render_speed(&self, factor: f32, sample_rate: u32, output_path: &str) -> Result<Self, AudioError> {
+        check_sample_rate(sample_rate)?;
+        let output_file = output_path.to_string();
+        let filter = atempo_chain(factor)?;
+        // Pin the rate explicitly; some output formats would otherwise drift from it.
+        let rate = sample_rate.to_string();
+        self.run_ffmpeg(&[], &["-filter:a", &filter, "-ar", &rate, &output_file], "change speed")?;
+        println!("Changed speed of {} by factor {} at {} Hz -> {}", self.file_path, factor, sample_rate, output_file);
+        Ok(self.derived(output_file))
     }
 
     /// Applies an audio effect using FFmpeg filters.
     pub fn apply_effect(&self, effect: AudioEffect) -> Result<Self, AudioError> {
-        let output_file = format!("effected_{}", self.file_path);
-        // Convert our enum into an FFmpeg filter string.
-        let filter = effect_to_filter(&effect);
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-i", &self.file_path, "-af", &filter, &output_file, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Applied effect {:?} on {} -> {}", effect, self.file_path, output_file);
-            Ok(AudioProcessor { file_path: output_file })
+        self.apply_effect_to(effect, &self.prefixed_output("effected"))
+    }
+
+    /// Like `apply_effect`, but writes the output to `output_path`.
+    pub fn apply_effect_to(&self, effect: AudioEffect, output_path: &str) -> Result<Self, AudioError> {
+        let args = self.effect_args(&effect, output_path)?;
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.run_ffmpeg(&[], &args, "apply effect")?;
+        println!("Applied effect {:?} on {} -> {}", effect, self.file_path, output_path);
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Builds the output arguments that render `effect` into `output_path`: an `-af`
+    /// chain, or for convolution the impulse response as a second input and a
+    /// `-filter_complex` graph.
+    fn effect_args(&self, effect: &AudioEffect, output_path: &str) -> Result<Vec<String>, AudioError> {
+        let filter = self.effect_filter(effect)?;
+        let mut args = Vec::new();
+        match effect {
+            AudioEffect::Convolution { impulse_response_path } => args.extend([
+                "-i".to_string(),
+                impulse_response_path.clone(),
+                "-filter_complex".to_string(),
+                format!("[0:a][1:a]{}", filter),
+            ]),
+            _ => args.extend(["-af".to_string(), filter]),
+        }
+        args.push(output_path.to_string());
+        Ok(args)
+    }
+
+    /// Applies an effect in parallel with the untouched signal, mixing `wet` (0.0 to 1.0)
+    /// of the effected audio with `1 - wet` of the dry audio, as in parallel compression.
+    /// The output lasts as long as the longer of the two, e.g. for effects with a tail.
+    pub fn apply_effect_mix(&self, effect: AudioEffect, wet: f32) -> Result<Self, AudioError> {
+        self.apply_effect_mix_to(effect, wet, &self.prefixed_output("effect_mixed"))
+    }
+
+    /// Like `apply_effect_mix`, but writes the output to `output_path`.
+    pub fn apply_effect_mix_to(&self, effect: AudioEffect, wet: f32, output_path: &str) -> Result<Self, AudioError> {
+        if !(0.0..=1.0).contains(&wet) {
+            return Err(AudioError::InvalidParameter(format!("wet mix must be 0.0-1.0, got {}", wet)));
+        }
+        let filter = self.effect_filter(&effect)?;
+        let mut args = Vec::new();
+        let wet_in = match &effect {
+            AudioEffect::Convolution { impulse_response_path } => {
+                args.extend(["-i", impulse_response_path.as_str()]);
+                "[wet_in][1:a]"
+            }
+            _ => "[wet_in]",
+        };
+        let graph = format!(
+            "[0:a]asplit=2[dry][wet_in];{}{}[wet];[dry][wet]amix=inputs=2:weights={} {}:normalize=0:duration=longest",
+            wet_in,
+            filter,
+            1.0 - wet,
+            wet
+        );
+        let output_file = output_path.to_string();
+        args.extend(["-filter_complex", &graph, &output_file]);
+        self.run_ffmpeg(&[], &args, "apply effect mix")?;
+        println!("Applied effect {:?} at {}% wet on {} -> {}", effect, wet * 100.0, self.file_path, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Applies an effect only between `start` and `end`, leaving the rest of the clip dry.
+    /// Effects whose filters support FFmpeg's timeline (see `AudioEffect::supports_timeline`)
+    /// get `enable='between(t,start,end)'` on each filter. Others are rendered over the
+    /// whole clip and switched in against the dry signal with timeline-gated `volume`
+    /// filters, so an echo or reverb tail is cut off at `end`. Fades and stutters, which
+    /// already act on a position in the clip, fail with `InvalidParameter`, as does an empty range.
+    pub fn apply_effect_ranged(&self, effect: AudioEffect, start: Duration, end: Duration) -> Result<Self, AudioError> {
+        self.apply_effect_ranged_to(effect, start, end, &self.prefixed_output("effect_ranged"))
+    }
+
+    /// Like `apply_effect_ranged`, but writes the output to `output_path`.
+    pub fn apply_effect_ranged_to(
+        &self,
+        effect: AudioEffect,
+        start: Duration,
+        end: Duration,
+        output_path: &str,
+    ) -> Result<Self, AudioError> {
+        if start >= end {
+            return Err(AudioError::InvalidParameter(format!(
+                "effect range start {:?} must be before end {:?}", start, end
+            )));
+        }
+        if matches!(effect, AudioEffect::FadeIn(_) | AudioEffect::FadeOut(_) | AudioEffect::Stutter { .. }) {
+            return Err(AudioError::InvalidParameter(format!("{:?} can't be limited to a time range", effect)));
+        }
+        let filter = self.effect_filter(&effect)?;
+        let (start_secs, end_secs) = (start.as_secs_f64(), end.as_secs_f64());
+        let output_file = output_path.to_string();
+        let mut args = Vec::new();
+        let graph;
+        if effect.supports_timeline() {
+            graph = timeline_filter(&filter, start_secs, end_secs);
+            args.extend(["-af", graph.as_str()]);
         } else {
-            Err(AudioError::FfmpegError("ffmpeg apply effect failed".to_string()))
+            let wet_in = match &effect {
+                AudioEffect::Convolution { impulse_response_path } => {
+                    args.extend(["-i", impulse_response_path.as_str()]);
+                    "[wet_in][1:a]"
+                }
+                _ => "[wet_in]",
+            };
+            let range = format!("between(t,{},{})", start_secs, end_secs);
+            graph = format!(
+                "[0:a]asplit=2[dry_in][wet_in];{}{},volume=0:enable='not({})'[wet];\
+                 [dry_in]volume=0:enable='{}'[dry];[dry][wet]amix=inputs=2:normalize=0:duration=first",
+                wet_in, filter, range, range
+            );
+            args.extend(["-filter_complex", graph.as_str()]);
+        }
+        args.push(&output_file);
+        self.run_ffmpeg(&[], &args, "apply ranged effect")?;
+        println!(
+            "Applied effect {:?} from {:?} to {:?} on {} -> {}",
+            effect, start, end, self.file_path, output_file
+        );
+        Ok(self.derived(output_file))
+    }
+
+    /// Validates `effect` against this input and builds its filter, probing the duration
+    /// or sample rate for effects that depend on them.
+    fn effect_filter(&self, effect: &AudioEffect) -> Result<String, AudioError> {
+        validate_effect(effect)?;
+        if let AudioEffect::Stutter { segment, at, .. } = effect {
+            let total = self.duration()?;
+            if *at + *segment > total {
+                return Err(AudioError::InvalidParameter(format!(
+                    "stutter segment {:?} at {:?} runs past the end of the clip ({:?})", segment, at, total
+                )));
+            }
+        }
+        if let AudioEffect::Convolution { impulse_response_path } = effect {
+            let streams = ffmpeg::probe(&self.config, impulse_response_path, "stream=codec_type", &["-select_streams", "a"])?;
+            if streams.trim().is_empty() {
+                return Err(AudioError::InvalidParameter(format!(
+                    "impulse response {} has no audio stream", impulse_response_path
+                )));
+            }
+        }
+        // Convert our enum into an FFmpeg filter string.
+        Ok(match effect {
+            AudioEffect::FadeOut(fade) => fade_out_filter(*fade, self.duration()?),
+            AudioEffect::PitchShift { semitones } => pitch_shift_filter(*semitones, self.sample_rate()?),
+            _ => effect_to_filter(effect),
+        })
+    }
+
+    /// Applies `AudioEffect::Compressor` and reports the maximum gain reduction in dB.
+    /// acompressor exposes no metering of its own, so the reduction is measured from the
+    /// mono mixdowns of input and output: the largest drop in short-term RMS level over
+    /// `COMPRESSOR_METER_WINDOW` blocks, with makeup gain taken out. Unlike a peak
+    /// comparison, this still registers when transients slip through during the attack.
+    pub fn apply_compressor_metered(&self, params: CompressorParams) -> Result<(Self, f32), AudioError> {
+        let makeup_db = params.makeup_db;
+        let compressed = self.apply_effect(AudioEffect::Compressor(params))?;
+        let sample_rate = self.sample_rate()?;
+        let rate = sample_rate.to_string();
+        let mono = ["-ac", "1", "-ar", rate.as_str()];
+        let input = ffmpeg::decode_f32(&self.config, &self.file_path, &[], &mono)?;
+        let output = ffmpeg::decode_f32(&compressed.config, &compressed.file_path, &[], &mono)?;
+        let window = (COMPRESSOR_METER_WINDOW.as_secs_f64() * f64::from(sample_rate)) as usize;
+        let reduction = analysis::max_gain_reduction_db(&input, &output, window, makeup_db);
+        println!("Compressor reduced the level of {} by up to {:.2} dB", self.file_path, reduction);
+        Ok((compressed, reduction))
+    }
+
+    /// Merges like `merge_audios` and also returns where each input starts in the result,
+    /// accumulated from the inputs' probed durations, e.g. for generating chapters.
+    pub fn merge_audios_with_offsets(audios: &[AudioProcessor], output_path: &str) -> Result<(Self, Vec<Duration>), AudioError> {
+        let mut offsets = Vec::with_capacity(audios.len());
+        let mut position = Duration::ZERO;
+        for audio in audios {
+            offsets.push(position);
+            position += audio.duration()?;
         }
+        let merged = Self::merge_audios(audios, output_path)?;
+        Ok((merged, offsets))
     }
 
     /// Merges multiple audio files sequentially (concatenation).
-    /// Uses FFmpeg’s concat demuxer.
+    /// Uses FFmpeg’s concat demuxer. Inputs with differing channel counts are handled
+    /// according to the first input's `merge_mismatch_policy`. If the stream-copied result's
+    /// duration doesn't match the inputs', it is re-encoded with the concat filter instead.
     pub fn merge_audios(audios: &[AudioProcessor], output_path: &str) -> Result<Self, AudioError> {
         use std::io::Write;
         use tempfile::NamedTempFile;
 
+        let config = audios.first().map(|audio| audio.config.clone()).unwrap_or_default();
+        let work_dir = tempfile::tempdir().map_err(AudioError::IoError)?;
+        let inputs = Self::conform_channels_for_merge(audios, &config, work_dir.path())?;
+
         // Create a temporary file listing all input files.
-        let mut list_file = NamedTempFile::new().map_err(|e| AudioError::IoError(e))?;
-        for audio in audios {
+        let mut list_file = NamedTempFile::new().map_err(AudioError::IoError)?;
+        for input in &inputs {
             // The concat demuxer expects lines like: file 'path/to/file'
-            writeln!(list_file, "file '{}'", audio.file_path).map_err(|e| AudioError::IoError(e))?;
+            // Relative entries would resolve against the list's temp directory.
+            let path = std::path::absolute(config.resolve_path(input)).map_err(AudioError::IoError)?;
+            writeln!(list_file, "{}", io::concat_list_entry(io::path_str(&path)?)).map_err(AudioError::IoError)?;
         }
-        list_file.flush().map_err(|e| AudioError::IoError(e))?;
+        list_file.flush().map_err(AudioError::IoError)?;
 
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-f", "concat", "-safe", "0", "-i", list_file.path().to_str().unwrap(), "-c", "copy", output_path, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
+        ffmpeg::run(
+            &config,
+            &["-y", "-f", "concat", "-safe", "0", "-i", io::path_str(list_file.path())?, "-c", "copy", output_path],
+            None,
+            "merge",
+        )?;
+        let merged = AudioProcessor { file_path: output_path.to_string(), config, pipe_input: None, _generated_input: None };
+        merged.verify_merge(&inputs)?;
+        println!("Merged {} audio files -> {}", audios.len(), output_path);
+        Ok(merged)
+    }
 
-        if status.success() {
-            println!("Merged {} audio files -> {}", audios.len(), output_path);
-            Ok(AudioProcessor { file_path: output_path.to_string() })
+    /// Checks a stream-copied merge: if this output's duration is off from the sum of the
+    /// inputs' by more than `MERGE_DURATION_TOLERANCE` per input, as when their timestamps
+    /// don't line up, logs a warning and re-renders it with the re-encoding concat filter,
+    /// resampling every input to the first one's rate. Verification is skipped, with a
+    /// warning, when a duration can't be probed.
+    fn verify_merge(&self, inputs: &[String]) -> Result<(), AudioError> {
+        let probe = |path: &str| {
+            self.derived(path.to_string()).duration().map_err(|e| {
+                log::warn!("can't verify merge {}: probing {} failed: {:?}", self.file_path, path, e);
+            })
+        };
+        let mut expected = Duration::ZERO;
+        for input in inputs {
+            let Ok(duration) = probe(input) else {
+                return Ok(());
+            };
+            expected += duration;
+        }
+        let Ok(actual) = probe(&self.file_path) else {
+            return Ok(());
+        };
+        let tolerance = MERGE_DURATION_TOLERANCE * inputs.len() as u32;
+        if actual.abs_diff(expected) <= tolerance {
+            return Ok(());
+        }
+        log::warn!(
+            "stream-copied merge {} lasts {:?} but its inputs sum to {:?}; re-encoding with the concat filter",
+            self.file_path, actual, expected
+        );
+        let Some(first) = inputs.first() else {
+            return Ok(());
+        };
+        let rate = self.derived(first.clone()).sample_rate()?;
+        let mut filter = String::new();
+        for index in 0..inputs.len() {
+            filter.push_str(&format!("[{i}:a]aresample={}[a{i}];", rate, i = index));
+        }
+        for index in 0..inputs.len() {
+            filter.push_str(&format!("[a{}]", index));
+        }
+        filter.push_str(&format!("concat=n={}:v=0:a=1", inputs.len()));
+        let mut args = vec!["-y"];
+        for input in inputs {
+            args.extend(["-i", input.as_str()]);
+        }
+        args.extend(["-filter_complex", &filter, &self.file_path]);
+        ffmpeg::run(&self.config, &args, None, "merge")
+    }
+
+    /// Merges multiple audio files sequentially, crossfading each into the next over
+    /// `crossfade` with `acrossfade`, so the result is `crossfade` shorter per join than a
+    /// plain `merge_audios`. The audio is re-encoded; every input is resampled to the first
+    /// one's sample rate. The crossfade must be shorter than the shortest input.
+    pub fn merge_with_crossfade(audios: &[AudioProcessor], crossfade: Duration, output_path: &str) -> Result<Self, AudioError> {
+        let first = audios.first().ok_or_else(|| {
+            AudioError::InvalidParameter("crossfade merge needs at least one input".to_string())
+        })?;
+        if crossfade.is_zero() {
+            return Err(AudioError::InvalidParameter("crossfade must be longer than zero".to_string()));
+        }
+        for audio in audios {
+            let duration = audio.duration()?;
+            if crossfade >= duration {
+                return Err(AudioError::InvalidParameter(format!(
+                    "crossfade {:?} is not shorter than {} ({:?})", crossfade, audio.file_path, duration
+                )));
+            }
+        }
+        let sample_rate = first.sample_rate()?;
+        let mut filter = String::new();
+        for index in 0..audios.len() {
+            filter.push_str(&format!("[{i}:a]aresample={}[r{i}];", sample_rate, i = index));
+        }
+        let mut previous = "r0".to_string();
+        for index in 1..audios.len() {
+            filter.push_str(&format!("[{}][r{}]acrossfade=d={}", previous, index, crossfade.as_secs_f64()));
+            if index + 1 < audios.len() {
+                previous = format!("x{}", index);
+                filter.push_str(&format!("[{}];", previous));
+            }
+        }
+        if audios.len() == 1 {
+            filter.push_str("[r0]anull");
+        }
+
+        let mut args = vec!["-y"];
+        for audio in audios {
+            args.extend(["-i", audio.file_path.as_str()]);
+        }
+        args.extend(["-filter_complex", &filter, output_path]);
+        ffmpeg::run(&first.config, &args, None, "crossfade merge")?;
+        println!("Merged {} audio files with {:?} crossfades -> {}", audios.len(), crossfade, output_path);
+        Ok(first.derived(output_path.to_string()))
+    }
+
+    /// Returns the paths to concatenate for `merge_audios`, re-encoding into `work_dir`
+    /// any input whose channel count differs from the policy's target.
+    fn conform_channels_for_merge(
+        audios: &[AudioProcessor],
+        config: &AudioProcessorConfig,
+        work_dir: &std::path::Path,
+    ) -> Result<Vec<String>, AudioError> {
+        let paths = audios.iter().map(|audio| audio.file_path.clone()).collect();
+        if audios.len() < 2 {
+            return Ok(paths);
+        }
+        let counts = audios.iter().map(AudioProcessor::channels).collect::<Result<Vec<_>, _>>()?;
+        if counts.iter().all(|&count| count == counts[0]) {
+            return Ok(paths);
+        }
+        let target = match config.merge_mismatch_policy {
+            MergeMismatchPolicy::Error => {
+                return Err(AudioError::InvalidParameter(format!(
+                    "cannot merge inputs with differing channel counts {:?}", counts
+                )));
+            }
+            MergeMismatchPolicy::ConformToFirst => counts[0],
+            MergeMismatchPolicy::ConformToMax => counts.iter().copied().max().unwrap_or(counts[0]),
+        };
+        let target_str = target.to_string();
+        audios
+            .iter()
+            .zip(&counts)
+            .enumerate()
+            .map(|(index, (audio, &count))| {
+                if count == target {
+                    return Ok(audio.file_path.clone());
+                }
+                let name = std::path::Path::new(&audio.file_path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let conformed = io::path_str(&work_dir.join(format!("{}_{}", index, name)))?.to_string();
+                audio.run_ffmpeg(&[], &["-ac", &target_str, &conformed], "conform channels for merge")?;
+                Ok(conformed)
+            })
+            .collect()
+    }
+
+    /// Mixes several audio files together, leaving the default 3 dB of headroom.
+    /// See `mix_with_headroom`.
+    pub fn mix(audios: &[AudioProcessor], output_path: &str) -> Result<Self, AudioError> {
+        Self::mix_with_headroom(audios, DEFAULT_MIX_HEADROOM_DB, output_path)
+    }
+
+    /// Mixes several audio files together without clipping.
+    /// Each input is pre-attenuated by the gain needed for N full-scale signals to sum to
+    /// `-headroom_db` dBFS, and an `alimiter` at the same ceiling catches anything left over.
+    /// The mix lasts as long as the longest input.
+    pub fn mix_with_headroom(audios: &[AudioProcessor], headroom_db: f32, output_path: &str) -> Result<Self, AudioError> {
+        let first = audios.first().ok_or_else(|| {
+            AudioError::InvalidParameter("mix needs at least one input".to_string())
+        })?;
+        if !(0.0..=24.0).contains(&headroom_db) {
+            return Err(AudioError::InvalidParameter(format!(
+                "mix headroom must be between 0 and 24 dB, got {}", headroom_db
+            )));
+        }
+        let count = audios.len();
+        let attenuation_db = linear_to_db(count as f32) + headroom_db;
+        let ceiling = db_to_linear(-headroom_db);
+        let mut filter = String::new();
+        for index in 0..count {
+            filter.push_str(&format!("[{i}]volume=-{}dB[a{i}];", attenuation_db, i = index));
+        }
+        for index in 0..count {
+            filter.push_str(&format!("[a{}]", index));
+        }
+        filter.push_str(&format!(
+            "amix=inputs={}:duration=longest:normalize=0,alimiter=limit={}", count, ceiling
+        ));
+
+        let mut args = vec!["-y"];
+        for audio in audios {
+            args.extend(["-i", audio.file_path.as_str()]);
+        }
+        args.extend(["-filter_complex", &filter, output_path]);
+        ffmpeg::run(&first.config, &args, None, "mix")?;
+        println!("Mixed {} audio files with {} dB headroom -> {}", count, headroom_db, output_path);
+        Ok(first.derived(output_path.to_string()))
+    }
+
+    /// Concatenates `[intro, self, outro]`, skipping whichever is `None`, into `output_path`.
+    /// The intro and outro are resampled and remixed to this clip's sample rate and channel
+    /// count first, so a mismatched intro doesn't break the concat.
+    pub fn add_intro_outro(&self, intro: Option<&AudioProcessor>, outro: Option<&AudioProcessor>, output_path: &str) -> Result<Self, AudioError> {
+        let parts: Vec<&AudioProcessor> = intro.into_iter().chain([self]).chain(outro).collect();
+        let sample_rate = self.sample_rate()?;
+        let layout = match self.channels()? {
+            1 => "mono".to_string(),
+            2 => "stereo".to_string(),
+            channels => format!("{}c", channels),
+        };
+        let mut filter = String::new();
+        for index in 0..parts.len() {
+            filter.push_str(&format!(
+                "[{i}:a]aresample={},aformat=sample_rates={}:channel_layouts={}[p{i}];",
+                sample_rate, sample_rate, layout, i = index
+            ));
+        }
+        for index in 0..parts.len() {
+            filter.push_str(&format!("[p{}]", index));
+        }
+        filter.push_str(&format!("concat=n={}:v=0:a=1", parts.len()));
+
+        let mut args = vec!["-y"];
+        for part in &parts {
+            args.extend(["-i", part.file_path.as_str()]);
+        }
+        args.extend(["-filter_complex", &filter, output_path]);
+        ffmpeg::run(&self.config, &args, None, "add intro/outro")?;
+        println!(
+            "Added intro {:?} and outro {:?} to {} -> {}",
+            intro.map(|intro| &intro.file_path), outro.map(|outro| &outro.file_path), self.file_path, output_path
+        );
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Re-encodes the audio to match `reference`'s codec, sample rate, channel count and
+    /// sample format in one render, so the two can be concatenated or mixed safely.
+    /// The output takes the reference's extension so the container suits the codec.
+    pub fn conform_to(&self, reference: &AudioProcessor) -> Result<Self, AudioError> {
+        let mut output = std::path::PathBuf::from(self.prefixed_output("conformed"));
+        if let Some(extension) = std::path::Path::new(&reference.file_path).extension() {
+            output.set_extension(extension);
+        }
+        self.conform_to_path(reference, &output.to_string_lossy())
+    }
+
+    /// Like `conform_to`, but writes the output to `output_path`, whose container must
+    /// be able to hold the reference's codec.
+    pub fn conform_to_path(&self, reference: &AudioProcessor, output_path: &str) -> Result<Self, AudioError> {
+        let codec = ffmpeg::probe(&reference.config, &reference.file_path, "stream=codec_name", &["-select_streams", "a:0"])?;
+        let sample_rate = reference.sample_rate()?.to_string();
+        let channels = reference.channels()?.to_string();
+        let sample_format = reference.sample_format()?;
+        let output_file = output_path.to_string();
+        self.run_ffmpeg(
+            &[],
+            &[
+                "-c:a", &codec,
+                "-ar", &sample_rate,
+                "-ac", &channels,
+                "-sample_fmt", &sample_format,
+                &output_file,
+            ],
+            "conform",
+        )?;
+        println!(
+            "Conformed {} to {} ({}, {} Hz, {} channels, {}) -> {}",
+            self.file_path, reference.file_path, codec, sample_rate, channels, sample_format, output_file
+        );
+        Ok(self.derived(output_file))
+    }
+
+    /// Writes each channel to its own mono file in `output_dir` (created if missing), named
+    /// after the input with a `_ch{n}` suffix, and returns them in channel order.
+    pub fn split_channels(&self, output_dir: &str) -> Result<Vec<Self>, AudioError> {
+        let count = usize::from(self.channels()?);
+        if count == 0 {
+            return Err(AudioError::InvalidParameter(format!("{} has no audio channels", self.file_path)));
+        }
+        let dir = std::path::Path::new(output_dir);
+        std::fs::create_dir_all(self.config.resolve_path(dir)).map_err(AudioError::IoError)?;
+        let input = std::path::Path::new(&self.file_path);
+        let stem = input.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+        let extension = input.extension().map(|ext| ext.to_string_lossy()).unwrap_or("wav".into());
+
+        let mut filter = format!("[0:a]asplit={}", count);
+        for index in 0..count {
+            filter.push_str(&format!("[s{}]", index));
+        }
+        let mut outputs = Vec::new();
+        for index in 0..count {
+            filter.push_str(&format!(";[s{i}]pan=mono|c0=c{i}[c{i}]", i = index));
+            let path = dir.join(format!("{}_ch{}.{}", stem, index, extension));
+            outputs.push((format!("[c{}]", index), path.to_string_lossy().into_owned()));
+        }
+        let mut args = vec!["-filter_complex", filter.as_str()];
+        for (label, path) in &outputs {
+            args.extend(["-map", label.as_str(), path.as_str()]);
+        }
+        self.run_ffmpeg(&[], &args, "split channels")?;
+        println!("Split {} into {} channels -> {}", self.file_path, count, output_dir);
+        Ok(outputs.into_iter().map(|(_, path)| self.derived(path)).collect())
+    }
+
+    /// Stacks the channels of several inputs, in order, into one multichannel file;
+    /// two mono inputs become a stereo file. The output ends with the shortest input.
+    pub fn join_channels(channels: &[AudioProcessor], output_path: &str) -> Result<Self, AudioError> {
+        let first = channels.first().ok_or_else(|| {
+            AudioError::InvalidParameter("join_channels needs at least one input".to_string())
+        })?;
+        let filter = if channels.len() == 1 {
+            "[0:a]anull".to_string()
         } else {
-            Err(AudioError::FfmpegError("ffmpeg merge failed".to_string()))
+            let labels: String = (0..channels.len()).map(|index| format!("[{}:a]", index)).collect();
+            format!("{}amerge=inputs={}", labels, channels.len())
+        };
+        let mut args = vec!["-y"];
+        for channel in channels {
+            args.extend(["-i", channel.file_path.as_str()]);
         }
+        args.extend(["-filter_complex", &filter, output_path]);
+        ffmpeg::run(&first.config, &args, None, "join channels")?;
+        println!("Joined {} inputs into {}", channels.len(), output_path);
+        Ok(first.derived(output_path.to_string()))
+    }
+
+    /// Splits the audio into mono channels, passes each through `per_channel` (called in
+    /// channel order) and joins the results into `output_path`. Useful for dual-mono
+    /// recordings where each channel carries a different speaker.
+    pub fn process_channels_independently(
+        &self,
+        per_channel: impl Fn(AudioProcessor) -> Result<AudioProcessor, AudioError>,
+        output_path: &str,
+    ) -> Result<Self, AudioError> {
+        let work_dir = tempfile::tempdir().map_err(AudioError::IoError)?;
+        let split = self.split_channels(io::path_str(work_dir.path())?)?;
+        let processed = split.into_iter().map(per_channel).collect::<Result<Vec<_>, _>>()?;
+        let joined = Self::join_channels(&processed, output_path)?;
+        Ok(self.derived(joined.file_path))
+    }
+
+    /// Cuts the audio into consecutive segments of `segment_length` (the last one may be
+    /// shorter), written to `output_dir` (created if missing) as `<stem>_000.<ext>`,
+    /// `<stem>_001.<ext>`, ... and returned in order.
+    pub fn split_into_segments(&self, segment_length: Duration, output_dir: &str) -> Result<Vec<Self>, AudioError> {
+        if segment_length.is_zero() {
+            return Err(AudioError::InvalidParameter("segment length must be greater than zero".to_string()));
+        }
+        let dir = std::path::Path::new(output_dir);
+        std::fs::create_dir_all(self.config.resolve_path(dir)).map_err(AudioError::IoError)?;
+        let input = std::path::Path::new(&self.file_path);
+        let stem = input.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+        let extension = input.extension().map(|ext| ext.to_string_lossy()).unwrap_or("wav".into());
+        let segment_path = |index: usize| dir.join(format!("{}_{:03}.{}", stem, index, extension));
+
+        let pattern = dir.join(format!("{}_%03d.{}", stem, extension)).to_string_lossy().into_owned();
+        let segment_secs = format!("{}", segment_length.as_secs_f64());
+        self.run_ffmpeg(
+            &[],
+            &["-f", "segment", "-segment_time", &segment_secs, "-reset_timestamps", "1", &pattern],
+            "split into segments",
+        )?;
+        let segments: Vec<Self> = (0..)
+            .map(segment_path)
+            .take_while(|path| self.config.resolve_path(path).exists())
+            .map(|path| self.derived(path.to_string_lossy().into_owned()))
+            .collect();
+        println!("Split {} into {} segments of {}s -> {}", self.file_path, segments.len(), segment_secs, output_dir);
+        Ok(segments)
+    }
+
+    /// Splits the audio with `split_into_segments`, then applies `op` to every segment,
+    /// running at most `max_parallel` ffmpeg processes at once. Processed segments are
+    /// written next to the split ones in `output_dir` and returned in order.
+    pub fn split_and_process(
+        &self,
+        segment_length: Duration,
+        op: Operation,
+        max_parallel: usize,
+        output_dir: &str,
+    ) -> Result<Vec<Self>, AudioError> {
+        if max_parallel == 0 {
+            return Err(AudioError::InvalidParameter("max_parallel must be at least 1".to_string()));
+        }
+        let segments = self.split_into_segments(segment_length, output_dir)?;
+        let processed = run_parallel(&segments, max_parallel, |segment| segment.apply(op.clone()))?;
+        println!("Processed {} segments of {} with {:?}", processed.len(), self.file_path, op);
+        Ok(processed)
     }
 
     /// Reverses the audio.
     pub fn reverse(&self) -> Result<Self, AudioError> {
-        let output_file = format!("reversed_{}", self.file_path);
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-i", &self.file_path, "-af", "areverse", &output_file, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Reversed audio {} -> {}", self.file_path, output_file);
-            Ok(AudioProcessor { file_path: output_file })
-        } else {
-            Err(AudioError::FfmpegError("ffmpeg reverse failed".to_string()))
+        self.reverse_to(&self.prefixed_output("reversed"))
+    }
+
+    /// Like `reverse`, but writes the output to `output_path`.
+    pub fn reverse_to(&self, output_path: &str) -> Result<Self, AudioError> {
+        let output_file = output_path.to_string();
+        self.run_ffmpeg(&[], &["-af", "areverse", &output_file], "reverse")?;
+        println!("Reversed audio {} -> {}", self.file_path, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Reverses the audio without holding the whole stream in memory.
+    /// The file is cut into `chunk`-long pieces, each piece is reversed on its own and the
+    /// pieces are concatenated in reverse order. Memory use is bounded by the chunk length,
+    /// at the cost of possible tiny discontinuities at chunk boundaries.
+    pub fn reverse_chunked(&self, chunk: Duration) -> Result<Self, AudioError> {
+        self.reverse_chunked_to(chunk, &self.prefixed_output("reversed_chunked"))
+    }
+
+    /// Like `reverse_chunked`, but writes the output to `output_path`.
+    pub fn reverse_chunked_to(&self, chunk: Duration, output_path: &str) -> Result<Self, AudioError> {
+        use std::io::Write;
+
+        if chunk.is_zero() {
+            return Err(AudioError::InvalidParameter("reverse chunk length must be greater than zero".to_string()));
+        }
+        let total = self.duration()?;
+        let output_file = output_path.to_string();
+        let work_dir = tempfile::tempdir().map_err(AudioError::IoError)?;
+
+        let mut chunk_paths = Vec::new();
+        let mut start = Duration::ZERO;
+        while start < total {
+            let chunk_path = work_dir.path().join(format!("chunk_{:05}.wav", chunk_paths.len()));
+            let chunk_path = io::path_str(&chunk_path)?.to_string();
+            let start_str = format!("{}", start.as_secs_f64());
+            let length_str = format!("{}", chunk.as_secs_f64());
+            self.run_ffmpeg(&["-ss", &start_str, "-t", &length_str], &["-af", "areverse", &chunk_path], "chunked reverse")?;
+            chunk_paths.push(chunk_path);
+            start += chunk;
+        }
+
+        // The last chunk of the input becomes the first chunk of the output.
+        let list_path = work_dir.path().join("chunks.txt");
+        let mut list_file = std::fs::File::create(&list_path).map_err(AudioError::IoError)?;
+        for chunk_path in chunk_paths.iter().rev() {
+            writeln!(list_file, "{}", io::concat_list_entry(chunk_path)).map_err(AudioError::IoError)?;
         }
+        list_file.flush().map_err(AudioError::IoError)?;
+
+        let list_path = io::path_str(&list_path)?;
+        ffmpeg::run(&self.config, &["-y", "-f", "concat", "-safe", "0", "-i", list_path, &output_file], None, "chunked reverse")?;
+        println!("Reversed audio {} in {} chunks -> {}", self.file_path, chunk_paths.len(), output_file);
+        Ok(self.derived(output_file))
     }
 
-    /// Normalizes the audio volume.
+    /// Normalizes the audio volume to loudnorm's default target of -24 LUFS.
     pub fn normalize(&self) -> Result<Self, AudioError> {
-        let output_file = format!("normalized_{}", self.file_path);
-        // Using loudnorm filter for normalization.
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-i", &self.file_path, "-af", "loudnorm", &output_file, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Normalized audio {} -> {}", self.file_path, output_file);
-            Ok(AudioProcessor { file_path: output_file })
-        } else {
-            Err(AudioError::FfmpegError("ffmpeg normalize failed".to_string()))
+        self.normalize_mode(NormalizeMode::Loudness { target_lufs: -24.0 })
+    }
+
+    /// Normalizes the audio so the measure chosen by `mode` reaches its target.
+    /// Peak and RMS modes measure the input with astats and apply a single gain, so the
+    /// dynamics are untouched; loudness mode uses loudnorm, as `normalize_loudness` does.
+    pub fn normalize_mode(&self, mode: NormalizeMode) -> Result<Self, AudioError> {
+        self.normalize_mode_to(mode, &self.prefixed_output("normalized"))
+    }
+
+    /// Like `normalize_mode`, but writes the output to `output_path`.
+    pub fn normalize_mode_to(&self, mode: NormalizeMode, output_path: &str) -> Result<Self, AudioError> {
+        let filter = match mode {
+            NormalizeMode::Loudness { target_lufs } => return self.normalize_loudness_to(target_lufs, false, output_path),
+            NormalizeMode::Peak { target_db } | NormalizeMode::Rms { target_db } => {
+                if !(-70.0..=0.0).contains(&target_db) {
+                    return Err(AudioError::InvalidParameter(format!(
+                        "target level must be between -70 and 0 dBFS, got {}", target_db
+                    )));
+                }
+                let stats = self.level_stats()?;
+                let measured = if matches!(mode, NormalizeMode::Peak { .. }) { stats.peak_db } else { stats.rms_db };
+                if !measured.is_finite() {
+                    return Err(AudioError::InvalidParameter(format!(
+                        "{} is silent and can't be normalized to a level", self.file_path
+                    )));
+                }
+                format!("volume={}dB", target_db - measured)
+            }
+        };
+        let output_file = output_path.to_string();
+        self.run_ffmpeg(&[], &["-af", &filter, &output_file], "normalize")?;
+        println!("Normalized audio {} with {:?} -> {}", self.file_path, mode, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Normalizes integrated loudness to `target_lufs` (EBU R128) with loudnorm. Set
+    /// `dual_mono` for mono recordings stored as two identical channels, so they are
+    /// measured as the mono signal they'll be heard as. The output keeps the input's
+    /// channel count, which some ffmpeg versions' loudnorm would otherwise change.
+    pub fn normalize_loudness(&self, target_lufs: f32, dual_mono: bool) -> Result<Self, AudioError> {
+        self.normalize_loudness_to(target_lufs, dual_mono, &self.prefixed_output("normalized"))
+    }
+
+    /// Like `normalize_loudness`, but writes the output to `output_path`.
+    pub fn normalize_loudness_to(&self, target_lufs: f32, dual_mono: bool, output_path: &str) -> Result<Self, AudioError> {
+        if !(-70.0..=-5.0).contains(&target_lufs) {
+            return Err(AudioError::InvalidParameter(format!(
+                "target loudness must be between -70 and -5 LUFS, got {}", target_lufs
+            )));
+        }
+        let channels = self.channels()?.to_string();
+        let filter = format!("loudnorm=I={}:dual_mono={}", target_lufs, dual_mono);
+        let output_file = output_path.to_string();
+        self.run_ffmpeg(&[], &["-af", &filter, "-ac", &channels, &output_file], "normalize")?;
+        println!("Normalized audio {} to {} LUFS (dual mono: {}) -> {}", self.file_path, target_lufs, dual_mono, output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Removes any DC offset, then normalizes loudness to `target_lufs` (EBU R128).
+    /// DC bias wastes headroom and skews loudnorm's measurement, so it is measured with
+    /// astats and cancelled with dcshift first. The shift is the mean offset across channels.
+    pub fn normalize_with_dc_removal(&self, target_lufs: f32) -> Result<Self, AudioError> {
+        self.normalize_with_dc_removal_to(target_lufs, &self.prefixed_output("normalized_dc"))
+    }
+
+    /// Like `normalize_with_dc_removal`, but writes the output to `output_path`.
+    pub fn normalize_with_dc_removal_to(&self, target_lufs: f32, output_path: &str) -> Result<Self, AudioError> {
+        if !(-70.0..=-5.0).contains(&target_lufs) {
+            return Err(AudioError::InvalidParameter(format!(
+                "target loudness {} LUFS is outside loudnorm's range [-70, -5]", target_lufs
+            )));
+        }
+        let dc_offset = self.level_stats()?.dc_offset;
+        let output_file = output_path.to_string();
+        let channels = self.channels()?.to_string();
+        let filter = format!("dcshift=shift={},loudnorm=I={}", -dc_offset, target_lufs);
+        self.run_ffmpeg(&[], &["-af", &filter, "-ac", &channels, &output_file], "normalize with dc removal")?;
+        println!(
+            "Removed DC offset {} and normalized {} to {} LUFS -> {}",
+            dc_offset, self.file_path, target_lufs, output_file
+        );
+        Ok(self.derived(output_file))
+    }
+
+    /// Normalizes a set of tracks to a shared album loudness.
+    /// Each track is measured, the album loudness is the duration-weighted energy average
+    /// of the tracks, and the single resulting gain is applied to every track so the
+    /// relative loudness between tracks is preserved. Outputs keep their file names
+    /// inside `output_dir`, so two tracks with the same file name fail with `InvalidParameter`.
+    pub fn normalize_album(files: &[&str], target_lufs: f32, output_dir: &str) -> Result<Vec<Self>, AudioError> {
+        Self::normalize_album_with_config(files, target_lufs, output_dir, AudioProcessorConfig::default())
+    }
+
+    /// Like `normalize_album`, but opens every track with `config`.
+    pub fn normalize_album_with_config(
+        files: &[&str],
+        target_lufs: f32,
+        output_dir: &str,
+        config: AudioProcessorConfig,
+    ) -> Result<Vec<Self>, AudioError> {
+        if files.is_empty() {
+            return Err(AudioError::InvalidParameter("album normalization needs at least one file".to_string()));
+        }
+        if !(-70.0..=-5.0).contains(&target_lufs) {
+            return Err(AudioError::InvalidParameter(format!(
+                "target loudness {} LUFS is outside the range [-70, -5]", target_lufs
+            )));
         }
+        let mut output_files = Vec::with_capacity(files.len());
+        for file in files {
+            let file_name = std::path::Path::new(file)
+                .file_name()
+                .ok_or_else(|| AudioError::InvalidParameter(format!("{} has no file name", file)))?;
+            let output_file = std::path::Path::new(output_dir).join(file_name).to_string_lossy().into_owned();
+            if output_files.contains(&output_file) {
+                return Err(AudioError::InvalidParameter(format!(
+                    "more than one album track is named {}; their outputs would overwrite each other",
+                    file_name.to_string_lossy()
+                )));
+            }
+            output_files.push(output_file);
+        }
+
+        let tracks = files
+            .iter()
+            .map(|file| AudioProcessor::with_config(file, config.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        std::fs::create_dir_all(config.resolve_path(output_dir)).map_err(AudioError::IoError)?;
+        let mut weighted_energy = 0.0;
+        let mut total_secs = 0.0;
+        for track in &tracks {
+            let loudness = track.integrated_loudness()? as f64;
+            let secs = track.duration()?.as_secs_f64();
+            weighted_energy += 10f64.powf(loudness / 10.0) * secs;
+            total_secs += secs;
+        }
+        if total_secs <= 0.0 {
+            return Err(AudioError::InvalidParameter("album tracks have no duration".to_string()));
+        }
+        let album_loudness = 10.0 * (weighted_energy / total_secs).log10();
+        let gain_db = target_lufs as f64 - album_loudness;
+        let filter = format!("volume={}dB", gain_db);
+
+        let mut outputs = Vec::with_capacity(tracks.len());
+        for (track, output_file) in tracks.iter().zip(output_files) {
+            track.run_ffmpeg(&[], &["-af", &filter, &output_file], "album normalize")?;
+            outputs.push(track.derived(output_file));
+        }
+        println!(
+            "Normalized {} tracks from album loudness {:.2} LUFS to {} LUFS (gain {:.2} dB) -> {}",
+            tracks.len(), album_loudness, target_lufs, gain_db, output_dir
+        );
+        Ok(outputs)
     }
 
     /// Overlays another audio onto this one at a given start time.
     pub fn overlay(&self, overlay_audio: &AudioProcessor, start_time: Duration) -> Result<Self, AudioError> {
-        let output_file = format!("overlayed_{}", self.file_path);
+        self.overlay_to(overlay_audio, start_time, &self.prefixed_output("overlayed"))
+    }
+
+    /// Like `overlay`, but writes the output to `output_path`.
+    pub fn overlay_to(&self, overlay_audio: &AudioProcessor, start_time: Duration, output_path: &str) -> Result<Self, AudioError> {
+        let output_file = output_path.to_string();
         // Using amix to mix two audio streams.
         // First, apply a delay to the overlay using the "adelay" filter.
         let delay_ms = start_time.as_millis();
-        let filter = format!("[1]adelay={}|{delay}|{delay}[d]; [0][d]amix=inputs=2:duration=first", delay=delay_ms);
-        let status = std::process::Command::new("ffmpeg")
-            .args(&["-i", &self.file_path, "-i", &overlay_audio.file_path, "-filter_complex", &filter, &output_file, "-y"])
-            .status()
-            .map_err(|e| AudioError::IoError(e))?;
-        if status.success() {
-            println!("Overlayed {} onto {} at {} seconds -> {}", overlay_audio.file_path, self.file_path, start_time.as_secs(), output_file);
-            Ok(AudioProcessor { file_path: output_file })
+        let filter = format!("[1]adelay={delay}|{delay}|{delay}[d]; [0][d]amix=inputs=2:duration=first", delay=delay_ms);
+        self.run_ffmpeg(&[], &["-i", &overlay_audio.file_path, "-filter_complex", &filter, &output_file], "overlay")?;
+        println!("Overlayed {} onto {} at {} seconds -> {}", overlay_audio.file_path, self.file_path, start_time.as_secs(), output_file);
+        Ok(self.derived(output_file))
+    }
+
+    /// Overlays several clips onto this one, each at its own start time, in a single render.
+    /// Every overlay is delayed with `adelay` and all streams are combined by one `amix`,
+    /// which is much cheaper than chaining `overlay` calls. The output keeps this clip's length.
+    pub fn overlay_many(&self, overlays: &[(AudioProcessor, Duration)], output_path: &str) -> Result<Self, AudioError> {
+        if overlays.is_empty() {
+            return Err(AudioError::InvalidParameter("overlay_many needs at least one overlay".to_string()));
+        }
+        for (overlay_audio, _) in overlays {
+            if !overlay_audio.config.input_is_accessible(&overlay_audio.file_path) {
+                return Err(AudioError::InvalidParameter(format!(
+                    "overlay input {} is not accessible", overlay_audio.file_path
+                )));
+            }
+        }
+        let mut filter = String::new();
+        let mut mix_inputs = String::from("[0]");
+        for (index, (_, start_time)) in overlays.iter().enumerate() {
+            let input = index + 1;
+            filter.push_str(&format!("[{i}]adelay=delays={}:all=1[d{i}];", start_time.as_millis(), i = input));
+            mix_inputs.push_str(&format!("[d{}]", input));
+        }
+        filter.push_str(&format!("{}amix=inputs={}:duration=first", mix_inputs, overlays.len() + 1));
+
+        let mut args = Vec::new();
+        for (overlay_audio, _) in overlays {
+            if io::is_lavfi_source(&overlay_audio.file_path) {
+                args.extend(["-f", "lavfi"]);
+            }
+            args.extend(["-i", overlay_audio.file_path.as_str()]);
+        }
+        args.extend(["-filter_complex", &filter, output_path]);
+        self.run_ffmpeg(&[], &args, "overlay many")?;
+        println!("Overlayed {} clips onto {} -> {}", overlays.len(), self.file_path, output_path);
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Returns true if both files decode to exactly the same samples.
+    /// Both inputs are decoded to the same canonical 32-bit PCM, so this verifies lossless
+    /// round-trips (e.g. WAV -> FLAC -> WAV) rather than perceptual similarity. Files with
+    /// a different sample rate, channel count or length are reported as `false`.
+    pub fn is_bit_identical(&self, other: &AudioProcessor) -> Result<bool, AudioError> {
+        if self.sample_rate()? != other.sample_rate()? || self.channels()? != other.channels()? {
+            println!("Compared {} with {}: sample rate or channels differ", self.file_path, other.file_path);
+            return Ok(false);
+        }
+        let ours = ffmpeg::decode_pcm(&self.config, &self.file_path, &[], "s32le", &[])?;
+        let theirs = ffmpeg::decode_pcm(&other.config, &other.file_path, &[], "s32le", &[])?;
+        let identical = ours == theirs;
+        println!("Compared {} with {}: bit identical = {}", self.file_path, other.file_path, identical);
+        Ok(identical)
+    }
+
+    /// Returns how much later the same audio occurs in `other` than in `self`, found by
+    /// cross-correlating mono 8 kHz decodes of both. Fails with `InvalidParameter` when
+    /// `other` runs ahead of `self`; `align_to` handles either direction.
+    pub fn find_offset(&self, other: &AudioProcessor) -> Result<Duration, AudioError> {
+        let lag = self.correlation_lag(other)?;
+        if lag < 0.0 {
+            return Err(AudioError::InvalidParameter(format!(
+                "{} runs {:.3}s ahead of {}; swap the arguments to measure it",
+                other.file_path, -lag, self.file_path
+            )));
+        }
+        Ok(Duration::from_secs_f64(lag))
+    }
+
+    /// Shifts `other` in time so it lines up with `self`: a late recording has the lag
+    /// trimmed from its start, an early one is padded with that much leading silence.
+    pub fn align_to(&self, other: &AudioProcessor) -> Result<Self, AudioError> {
+        self.align_to_path(other, &other.prefixed_output("aligned"))
+    }
+
+    /// Like `align_to`, but writes the shifted copy of `other` to `output_path`.
+    pub fn align_to_path(&self, other: &AudioProcessor, output_path: &str) -> Result<Self, AudioError> {
+        let lag = self.correlation_lag(other)?;
+        let filter = if lag >= 0.0 {
+            format!("atrim=start={},asetpts=PTS-STARTPTS", lag)
         } else {
-            Err(AudioError::FfmpegError("ffmpeg overlay failed".to_string()))
+            format!("adelay=delays={}:all=1", (-lag * 1000.0).round() as u64)
+        };
+        let output_file = output_path.to_string();
+        other.run_ffmpeg(&[], &["-af", &filter, &output_file], "align")?;
+        println!("Aligned {} to {} (lag {:.4}s) -> {}", other.file_path, self.file_path, lag, output_file);
+        Ok(other.derived(output_file))
+    }
+
+    /// Returns the signed lag of `other` behind `self`, in seconds.
+    fn correlation_lag(&self, other: &AudioProcessor) -> Result<f64, AudioError> {
+        let rate = ALIGNMENT_SAMPLE_RATE.to_string();
+        let decode = |processor: &AudioProcessor| -> Result<Vec<f32>, AudioError> {
+            ffmpeg::decode_f32(&processor.config, &processor.file_path, &[], &["-ac", "1", "-ar", &rate])
+        };
+        let lag = analysis::correlation_lag(&decode(self)?, &decode(other)?);
+        Ok(lag as f64 / f64::from(ALIGNMENT_SAMPLE_RATE))
+    }
+}
+
+/// Checks a requested output sample rate against the range the crate supports.
+fn check_sample_rate(sample_rate: u32) -> Result<(), AudioError> {
+    if !(8000..=192_000).contains(&sample_rate) {
+        return Err(AudioError::InvalidParameter(format!(
+            "sample rate must be between 8000 and 192000 Hz, got {}", sample_rate
+        )));
+    }
+    Ok(())
+}
+
+/// Input options for `trim`: `-ss` and `-to` before the input cut without re-encoding.
+fn trim_input_opts<'a>(start: &'a str, end: &'a str) -> [&'a str; 4] {
+    ["-ss", start, "-to", end]
+}
+
+/// Output arguments that copy the streams untouched into `output_file`, for cuts made
+/// by input options such as a fast `-ss` seek.
+fn stream_copy_args(output_file: &str) -> [&str; 3] {
+    ["-c", "copy", output_file]
+}
+
+/// Calls `task` on every item using up to `max_parallel` worker threads and returns the
+/// results in item order. Stops handing out work after the first failure, which is returned.
+fn run_parallel<T: Sync, R: Send>(
+    items: &[T],
+    max_parallel: usize,
+    task: impl Fn(&T) -> Result<R, AudioError> + Sync,
+) -> Result<Vec<R>, AudioError> {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Vec<std::sync::Mutex<Option<Result<R, AudioError>>>> =
+        items.iter().map(|_| std::sync::Mutex::new(None)).collect();
+    std::thread::scope(|scope| {
+        for _ in 0..max_parallel.min(items.len()) {
+            scope.spawn(|| {
+                while !failed.load(Ordering::SeqCst) {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(item) = items.get(index) else { break };
+                    let result = task(item);
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    *results[index].lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(result);
+                }
+            });
+        }
+    });
+    let mut outputs = Vec::with_capacity(items.len());
+    for result in results {
+        match result.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+            Some(Ok(output)) => outputs.push(output),
+            Some(Err(e)) => return Err(e),
+            // Skipped after another item failed; that error is returned below.
+            None => {}
+        }
+    }
+    Ok(outputs)
+}
+
+fn parse_secs(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|secs| secs.is_finite() && *secs >= 0.0)
+}
+
+/// Escapes the characters the tee muxer treats as separators or option delimiters.
+fn escape_tee_path(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '\\' | '|' | '[' | ']') {
+            escaped.push('\\');
         }
+        escaped.push(c);
     }
+    escaped
 }
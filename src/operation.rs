@@ -0,0 +1,90 @@
+// operation.rs
+
+use crate::errors::AudioError;
+use crate::processing::AudioEffect;
+use crate::transcoding::AudioFormat;
+use crate::AudioProcessor;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::Duration;
+
+/// Allowance for headers and container framing on top of the encoded audio.
+const CONTAINER_OVERHEAD_BYTES: u64 = 64 * 1024;
+
+/// A single processing step, run by `AudioProcessor::apply` and sized by
+/// `estimate_output_space`. Each variant maps onto the method of the same name.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Trim { start: Duration, end: Duration },
+    Seek(Duration),
+    Volume(f32),
+    Speed(f32),
+    Effect(AudioEffect),
+    Reverse,
+    Normalize,
+    /// Transcodes to `transcoded_<stem>.<format>` next to the input.
+    Transcode(AudioFormat),
+}
+
+impl AudioProcessor {
+    /// Runs `op` through the matching method (`trim`, `adjust_volume`, ...) and returns
+    /// the processor for its output, so steps can be stored and replayed as values.
+    pub fn apply(&self, op: Operation) -> Result<Self, AudioError> {
+        match op {
+            Operation::Trim { start, end } => self.trim(start, end),
+            Operation::Seek(position) => self.seek(position),
+            Operation::Volume(factor) => self.adjust_volume(factor),
+            Operation::Speed(factor) => self.change_speed(factor),
+            Operation::Effect(effect) => self.apply_effect(effect),
+            Operation::Reverse => self.reverse(),
+            Operation::Normalize => self.normalize(),
+            Operation::Transcode(format) => {
+                let output_path = Path::new(&self.prefixed_output("transcoded"))
+                    .with_extension(format.extension())
+                    .to_string_lossy()
+                    .into_owned();
+                self.transcode(format, &output_path)?;
+                Ok(self.derived(output_path))
+            }
+        }
+    }
+
+    /// Estimates how many bytes `op` will write. Operations that keep the input's format
+    /// are assumed to keep its average byte rate; transcodes use the target format's
+    /// default encoder settings. The estimate includes a margin for container overhead.
+    pub fn estimate_output_space(&self, op: &Operation) -> Result<u64, AudioError> {
+        let total = self.duration()?;
+        let output_duration = match op {
+            Operation::Trim { start, end } => (*end).min(total).saturating_sub(*start),
+            Operation::Seek(position) => total.saturating_sub(*position),
+            Operation::Speed(factor) => total.div_f64(f64::from(self.config.speed_factor(*factor)?)),
+            Operation::Effect(AudioEffect::Stutter { segment, repeats, .. }) => total + *segment * *repeats,
+            _ => total,
+        };
+        let input_size = std::fs::metadata(self.config.resolve_path(&self.file_path)).map(|metadata| metadata.len());
+        let bytes_per_sec = match (op, input_size) {
+            (Operation::Transcode(format), _) => format.estimated_byte_rate(self.sample_rate()?, self.channels()?),
+            (_, Ok(size)) if !total.is_zero() => size as f64 / total.as_secs_f64(),
+            // Streams and generated sources have no size to go by; assume 16-bit PCM.
+            _ => AudioFormat::Wav.estimated_byte_rate(self.sample_rate()?, self.channels()?),
+        };
+        Ok((output_duration.as_secs_f64() * bytes_per_sec).ceil() as u64 + CONTAINER_OVERHEAD_BYTES)
+    }
+
+    /// Checks that the filesystem holding `output_path` has room for `op`'s output,
+    /// failing with a `StorageFull` I/O error before any rendering starts if it doesn't.
+    /// Returns the estimate from `estimate_output_space`.
+    pub fn ensure_output_space(&self, op: &Operation, output_path: &str) -> Result<u64, AudioError> {
+        let needed = self.estimate_output_space(op)?;
+        let output = self.config.resolve_path(output_path);
+        let dir = output.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let available = fs4::available_space(dir).map_err(AudioError::IoError)?;
+        if available < needed {
+            return Err(AudioError::IoError(std::io::Error::new(
+                ErrorKind::StorageFull,
+                format!("{} needs about {} bytes but only {} are free in {}", output_path, needed, available, dir.display()),
+            )));
+        }
+        Ok(needed)
+    }
+}
@@ -1,13 +1,106 @@
 use std::time::Duration;
+use crate::config::AudioProcessorConfig;
 use crate::errors::AudioError;
+use crate::ffmpeg;
+
+/// Converts a gain in decibels to a linear amplitude factor (0 dB -> 1.0, +6 dB -> ~2.0).
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Converts a linear amplitude factor to decibels (1.0 -> 0 dB).
+/// Factors of zero or below have no finite level and return negative infinity.
+pub fn linear_to_db(factor: f32) -> f32 {
+    if factor <= 0.0 {
+        f32::NEG_INFINITY
+    } else {
+        20.0 * factor.log10()
+    }
+}
+
+/// Settings for the dynamic range compressor (FFmpeg's acompressor).
+#[derive(Debug, Clone)]
+pub struct CompressorParams {
+    /// Level above which gain reduction starts, in dBFS (-60 to 0).
+    pub threshold_db: f32,
+    /// Compression ratio (1 to 20).
+    pub ratio: f32,
+    /// Time for gain reduction to engage.
+    pub attack: Duration,
+    /// Time for gain reduction to recover.
+    pub release: Duration,
+    /// Gain applied after compression, in dB (0 to 36).
+    pub makeup_db: f32,
+}
+
+impl Default for CompressorParams {
+    fn default() -> Self {
+        CompressorParams {
+            threshold_db: -18.0,
+            ratio: 4.0,
+            attack: Duration::from_millis(20),
+            release: Duration::from_millis(250),
+            makeup_db: 0.0,
+        }
+    }
+}
+
+/// What `AudioProcessor::normalize_mode` measures and the level it brings that measure to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalizeMode {
+    /// Sample peak, in dBFS.
+    Peak { target_db: f32 },
+    /// Integrated loudness (EBU R128), in LUFS.
+    Loudness { target_lufs: f32 },
+    /// RMS level, in dBFS.
+    Rms { target_db: f32 },
+}
 
 /// Enum for available audio effects.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AudioEffect {
     FadeIn(Duration),
     FadeOut(Duration),
     Echo { delay: Duration, decay: f32 },
-    // Additional effects (e.g., reverb) can be added here.
+    /// Repeats the `segment`-long slice starting at `at` an extra `repeats` times in place,
+    /// lengthening the clip by `segment * repeats`.
+    Stutter { segment: Duration, repeats: u32, at: Duration },
+    /// Lo-fi bit-depth (1–16 bits) and sample-rate reduction; `rate_divisor` holds each
+    /// sample for that many samples (1 leaves the rate untouched).
+    Bitcrush { bits: u8, rate_divisor: u32 },
+    Compressor(CompressorParams),
+    /// AM-radio voice preset: band-limited to 300–3000 Hz, heavily compressed and
+    /// lightly saturated.
+    RadioVoice,
+    /// Soft-clipping distortion: `gain` (> 1.0) drives the signal into a tanh waveshaper,
+    /// then `tone` (0.0 dark to 1.0 bright) sets a lowpass that tames the harsh harmonics.
+    Overdrive { gain: f32, tone: f32 },
+    /// Shifts pitch by `semitones` (-24 to 24, negative is lower) without changing the
+    /// duration, by resampling and then restoring the tempo with atempo.
+    PitchShift { semitones: f32 },
+    /// Multi-tap echo reverb. `room_size` stretches the taps and lengthens the tail,
+    /// `damping` makes later reflections die away faster and `wet` sets how loud the
+    /// reflections are against the direct sound. All three range from 0.0 to 1.0.
+    Reverb { room_size: f32, damping: f32, wet: f32 },
+    /// Convolves the input with the impulse response recorded in the audio file at
+    /// `impulse_response_path` (e.g. a room or a speaker cabinet), using afir. The
+    /// impulse response is a second input, so this effect renders with `-filter_complex`.
+    Convolution { impulse_response_path: String },
+    // Additional effects can be added here.
+}
+
+impl AudioEffect {
+    /// Returns true if every filter this effect renders supports FFmpeg's timeline option
+    /// (`enable=`), so `apply_effect_ranged` can switch it on and off in place: `Bitcrush`,
+    /// `Compressor`, `RadioVoice` and `Overdrive`. The others (`Echo`, `Reverb`,
+    /// `PitchShift`, `Convolution`) are ranged by gating a processed copy against the dry
+    /// signal, and `FadeIn`, `FadeOut` and `Stutter` can't be ranged.
+    pub fn supports_timeline(&self) -> bool {
+        matches!(
+            self,
+            AudioEffect::Bitcrush { .. } | AudioEffect::Compressor(_) | AudioEffect::RadioVoice | AudioEffect::Overdrive { .. }
+        )
+    }
 }
 
 /// Converts an AudioEffect into an FFmpeg filter string.
@@ -18,41 +111,369 @@ pub fn effect_to_filter(effect: &AudioEffect) -> String {
             format!("afade=t=in:st=0:d={}", dur.as_secs_f32())
         }
         AudioEffect::FadeOut(dur) => {
-            // Assuming fade out at the end (this is a simplification)
-            format!("afade=t=out:st=0:d={}", dur.as_secs_f32())
+            // Without the clip length, fade in the reversed audio and flip it back.
+            // `apply_effect` knows the length and uses `fade_out_filter` instead.
+            format!("areverse,afade=t=in:st=0:d={},areverse", dur.as_secs_f32())
         }
         AudioEffect::Echo { delay, decay } => {
             // Using a simple aecho filter.
             // Format: aecho=in_gain:out_gain:delays:decays
             format!("aecho=0.8:0.9:{}:{}", delay.as_millis(), decay)
         }
+        AudioEffect::Stutter { segment, repeats, at } => {
+            // Split the input, cut [0, at), `repeats` copies of the segment and [at, end),
+            // then concatenate them back together in order.
+            let start = at.as_secs_f64();
+            let end = (*at + *segment).as_secs_f64();
+            let has_head = !at.is_zero();
+            let branches = *repeats as usize + 1 + usize::from(has_head);
+            let labels: Vec<String> = (0..branches).map(|index| format!("[s{}]", index)).collect();
+            let mut graph = format!("asplit={}{}", branches, labels.concat());
+            let mut parts = Vec::with_capacity(branches);
+            let mut index = 0;
+            if has_head {
+                graph.push_str(&format!(";[s0]atrim=end={},asetpts=PTS-STARTPTS[p0]", start));
+                parts.push("[p0]".to_string());
+                index += 1;
+            }
+            for _ in 0..*repeats {
+                graph.push_str(&format!(
+                    ";[s{i}]atrim=start={}:end={},asetpts=PTS-STARTPTS[p{i}]", start, end, i = index
+                ));
+                parts.push(format!("[p{}]", index));
+                index += 1;
+            }
+            graph.push_str(&format!(";[s{i}]atrim=start={},asetpts=PTS-STARTPTS[p{i}]", start, i = index));
+            parts.push(format!("[p{}]", index));
+            graph.push_str(&format!(";{}concat=n={}:v=0:a=1", parts.concat(), parts.len()));
+            graph
+        }
+        AudioEffect::Bitcrush { bits, rate_divisor } => {
+            format!("acrusher=bits={}:samples={}", bits, rate_divisor)
+        }
+        AudioEffect::Compressor(params) => {
+            // acompressor takes threshold and makeup as linear gains, times in milliseconds.
+            format!(
+                "acompressor=threshold={}:ratio={}:attack={}:release={}:makeup={}",
+                db_to_linear(params.threshold_db),
+                params.ratio,
+                params.attack.as_secs_f64() * 1000.0,
+                params.release.as_secs_f64() * 1000.0,
+                db_to_linear(params.makeup_db)
+            )
+        }
+        AudioEffect::RadioVoice => {
+            // Cascaded filters give a steeper 4-pole band edge than a single `bandpass`.
+            "highpass=f=300,highpass=f=300,lowpass=f=3000,lowpass=f=3000,\
+             acompressor=threshold=0.1:ratio=8:attack=5:release=100:makeup=2,\
+             asoftclip=type=tanh"
+                .to_string()
+        }
+        AudioEffect::Overdrive { gain, tone } => {
+            let cutoff = 1000.0 + tone * 11000.0;
+            format!("volume={},asoftclip=type=tanh,lowpass=f={}", gain, cutoff)
+        }
+        // The input rate isn't known here, so 44.1 kHz is assumed; `apply_effect` probes
+        // the real rate and uses `pitch_shift_filter` directly.
+        AudioEffect::PitchShift { semitones } => pitch_shift_filter(*semitones, 44100),
+        AudioEffect::Reverb { room_size, damping, wet } => {
+            // Mutually prime tap spacings keep the reflections from reinforcing each other.
+            let taps = [23.0, 31.0, 41.0, 53.0, 67.0, 79.0];
+            let scale = 1.0 + 3.0 * room_size;
+            let mut decay = (0.2 + 0.6 * room_size) * wet;
+            let mut delays = Vec::with_capacity(taps.len());
+            let mut decays = Vec::with_capacity(taps.len());
+            for tap in taps {
+                delays.push(format!("{:.1}", tap * scale));
+                // aecho rejects a decay of exactly zero.
+                decays.push(decay.max(0.0001));
+                decay *= 1.0 - 0.7 * damping;
+            }
+            // Scale the output down by the echo gain so the sum doesn't clip.
+            let out_gain = 1.0 / (1.0 + decays.iter().sum::<f32>());
+            let decays: Vec<String> = decays.iter().map(|decay| format!("{:.4}", decay)).collect();
+            format!("aecho=1:{:.4}:{}:{}", out_gain, delays.join("|"), decays.join("|"))
+        }
+        // afir takes the impulse response on its second pad, which `apply_effect` feeds
+        // from a second `-i`.
+        AudioEffect::Convolution { .. } => "afir".to_string(),
+    }
+}
+
+/// Builds the pitch shift for input at `sample_rate`: `asetrate` plays the samples
+/// `2^(semitones/12)` faster, `aresample` restores the rate and atempo undoes the change
+/// in length.
+pub(crate) fn pitch_shift_filter(semitones: f32, sample_rate: u32) -> String {
+    let ratio = 2f64.powf(f64::from(semitones) / 12.0);
+    let shifted_rate = (f64::from(sample_rate) * ratio).round() as u64;
+    // The tempo factor is the rate actually set, so rounding doesn't drift the length.
+    let tempo = sample_rate as f32 / shifted_rate as f32;
+    format!("asetrate={},aresample={},{}", shifted_rate, sample_rate, atempo_stages(tempo))
+}
+
+/// Adds the timeline option `enable='between(t,start,end)'` to every filter in `chain`,
+/// so each only processes audio between `start` and `end` seconds and passes the rest
+/// through untouched.
+pub(crate) fn timeline_filter(chain: &str, start: f64, end: f64) -> String {
+    let enable = format!("enable='between(t,{},{})'", start, end);
+    chain
+        .split(',')
+        .map(|filter| {
+            let separator = if filter.contains('=') { ':' } else { '=' };
+            format!("{}{}{}", filter.trim(), separator, enable)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Checks an effect's parameters before any filtergraph is built.
+pub(crate) fn validate_effect(effect: &AudioEffect) -> Result<(), AudioError> {
+    match effect {
+        AudioEffect::Stutter { segment, repeats, .. } => {
+            if segment.is_zero() {
+                return Err(AudioError::InvalidParameter("stutter segment must be longer than zero".to_string()));
+            }
+            if *repeats == 0 {
+                return Err(AudioError::InvalidParameter("stutter needs at least one repeat".to_string()));
+            }
+            Ok(())
+        }
+        AudioEffect::Bitcrush { bits, rate_divisor } => {
+            if !(1..=16).contains(bits) {
+                return Err(AudioError::InvalidParameter(format!("bitcrush bits must be 1-16, got {}", bits)));
+            }
+            // acrusher accepts a sample-hold factor of at most 250.
+            if !(1..=250).contains(rate_divisor) {
+                return Err(AudioError::InvalidParameter(format!(
+                    "bitcrush rate divisor must be 1-250, got {}", rate_divisor
+                )));
+            }
+            Ok(())
+        }
+        AudioEffect::Compressor(params) => {
+            if !(-60.0..=0.0).contains(&params.threshold_db) {
+                return Err(AudioError::InvalidParameter(format!(
+                    "compressor threshold must be -60 to 0 dB, got {}", params.threshold_db
+                )));
+            }
+            if !(1.0..=20.0).contains(&params.ratio) {
+                return Err(AudioError::InvalidParameter(format!("compressor ratio must be 1-20, got {}", params.ratio)));
+            }
+            if !(0.0..=36.0).contains(&params.makeup_db) {
+                return Err(AudioError::InvalidParameter(format!(
+                    "compressor makeup gain must be 0-36 dB, got {}", params.makeup_db
+                )));
+            }
+            let attack_ms = params.attack.as_secs_f64() * 1000.0;
+            let release_ms = params.release.as_secs_f64() * 1000.0;
+            if !(0.01..=2000.0).contains(&attack_ms) || !(0.01..=9000.0).contains(&release_ms) {
+                return Err(AudioError::InvalidParameter(
+                    "compressor attack must be 0.01-2000 ms and release 0.01-9000 ms".to_string(),
+                ));
+            }
+            Ok(())
+        }
+        AudioEffect::Overdrive { gain, tone } => {
+            if !gain.is_finite() || *gain <= 1.0 {
+                return Err(AudioError::InvalidParameter(format!("overdrive gain must be greater than 1.0, got {}", gain)));
+            }
+            if !(0.0..=1.0).contains(tone) {
+                return Err(AudioError::InvalidParameter(format!("overdrive tone must be 0.0-1.0, got {}", tone)));
+            }
+            Ok(())
+        }
+        AudioEffect::Reverb { room_size, damping, wet } => {
+            for (name, value) in [("room size", room_size), ("damping", damping), ("wet", wet)] {
+                if !(0.0..=1.0).contains(value) {
+                    return Err(AudioError::InvalidParameter(format!("reverb {} must be 0.0-1.0, got {}", name, value)));
+                }
+            }
+            Ok(())
+        }
+        AudioEffect::PitchShift { semitones } => {
+            if !(-24.0..=24.0).contains(semitones) {
+                return Err(AudioError::InvalidParameter(format!(
+                    "pitch shift must be -24 to 24 semitones, got {}", semitones
+                )));
+            }
+            Ok(())
+        }
+        AudioEffect::Convolution { impulse_response_path } => {
+            if !std::path::Path::new(impulse_response_path).is_file() {
+                return Err(AudioError::InvalidParameter(format!(
+                    "impulse response {} is not a file", impulse_response_path
+                )));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
     }
 }
 
 /// Reverses an audio file using FFmpeg’s areverse filter.
 pub fn reverse_audio(input_path: &str, output_path: &str) -> Result<(), AudioError> {
     println!("Reversing audio: {} -> {}", input_path, output_path);
-    let status = std::process::Command::new("ffmpeg")
-        .args(&["-i", input_path, "-af", "areverse", output_path, "-y"])
-        .status()
-        .map_err(|e| AudioError::IoError(e))?;
-    if status.success() {
-        Ok(())
-    } else {
-        Err(AudioError::FfmpegError("ffmpeg reverse failed".to_string()))
+    run_filter(input_path, output_path, "areverse", "reverse")
+}
+
+/// Returns the `atempo` filter chain that changes tempo by `factor`, e.g. `atempo=2,atempo=2`
+/// for 4x. A single atempo instance only accepts 0.5 to 2.0, so larger changes are split
+/// into a chain of stages whose product is `factor`, using as few stages as possible.
+pub fn atempo_chain(factor: f32) -> Result<String, AudioError> {
+    if !factor.is_finite() || factor <= 0.0 {
+        return Err(AudioError::InvalidParameter(format!(
+            "tempo factor must be a positive finite number, got {}", factor
+        )));
+    }
+    Ok(atempo_stages(factor))
+}
+
+/// Builds the chain for `atempo_chain` once `factor` is known to be positive and finite.
+fn atempo_stages(factor: f32) -> String {
+    let mut stages = Vec::new();
+    let mut remaining = factor;
+    while remaining > 2.0 {
+        stages.push(2.0);
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        stages.push(0.5);
+        remaining /= 0.5;
     }
+    if remaining != 1.0 || stages.is_empty() {
+        stages.push(remaining);
+    }
+    stages.iter().map(|stage| format!("atempo={}", stage)).collect::<Vec<_>>().join(",")
+}
+
+/// Checks that an `-af` filter chain parses and links before it is used for a real render.
+/// The error carries ffmpeg's explanation, e.g. an unknown filter or option name.
+pub fn validate_filtergraph(graph: &str) -> Result<(), AudioError> {
+    ffmpeg::validate_filtergraph(&AudioProcessorConfig::default(), graph, false)
 }
 
 /// Normalizes the audio volume using FFmpeg’s loudnorm filter.
 pub fn normalize_volume(input_path: &str, output_path: &str) -> Result<(), AudioError> {
     println!("Normalizing volume: {} -> {}", input_path, output_path);
-    let status = std::process::Command::new("ffmpeg")
-        .args(&["-i", input_path, "-af", "loudnorm", output_path, "-y"])
-        .status()
-        .map_err(|e| AudioError::IoError(e))?;
-    if status.success() {
-        Ok(())
-    } else {
-        Err(AudioError::FfmpegError("ffmpeg normalize failed".to_string()))
+    run_filter(input_path, output_path, "loudnorm", "normalize")
+}
+
+/// Builds a fade-out that ends with the clip: it starts `duration` before `total`, or at
+/// the start if the clip is shorter than the fade.
+pub(crate) fn fade_out_filter(duration: Duration, total: Duration) -> String {
+    let start = total.saturating_sub(duration);
+    format!("afade=t=out:st={}:d={}", start.as_secs_f64(), duration.as_secs_f64())
+}
+
+/// Fades in an audio file over `duration`, like `apply_effect(AudioEffect::FadeIn(..))`
+/// but without creating an `AudioProcessor`.
+pub fn fade_in(input_path: &str, output_path: &str, duration: Duration) -> Result<(), AudioError> {
+    println!("Fading in audio over {:?}: {} -> {}", duration, input_path, output_path);
+    run_filter(input_path, output_path, &effect_to_filter(&AudioEffect::FadeIn(duration)), "fade in")
+}
+
+/// Scales an audio file's volume by `factor`, like `AudioProcessor::adjust_volume` but
+/// without creating an `AudioProcessor`. The factor is applied as-is, with no clipping check.
+pub fn set_volume(input_path: &str, output_path: &str, factor: f32) -> Result<(), AudioError> {
+    let filter = volume_filter(factor)?;
+    println!("Setting volume by factor {}: {} -> {}", factor, input_path, output_path);
+    run_filter(input_path, output_path, &filter, "set volume")
+}
+
+/// Builds the `volume` filter for a linear gain factor. Negative factors (which would
+/// invert the phase) and non-finite ones are rejected; 0.0 mutes.
+pub(crate) fn volume_filter(factor: f32) -> Result<String, AudioError> {
+    if !factor.is_finite() || factor < 0.0 {
+        return Err(AudioError::InvalidParameter(format!(
+            "volume factor must be a finite number of at least 0.0, got {}", factor
+        )));
+    }
+    Ok(format!("volume={}", factor))
+}
+
+/// Builds the `volume` filter for a gain in decibels, which the filter accepts directly
+/// with a `dB` suffix. Gains outside -96..=48 dB, or non-finite ones, are rejected.
+pub(crate) fn volume_db_filter(gain_db: f32) -> Result<String, AudioError> {
+    if !(-96.0..=48.0).contains(&gain_db) {
+        return Err(AudioError::InvalidParameter(format!(
+            "volume gain must be between -96 and 48 dB, got {}", gain_db
+        )));
+    }
+    Ok(format!("volume={}dB", gain_db))
+}
+
+/// Runs `filter` from `input_path` to `output_path` with the default config.
+fn run_filter(input_path: &str, output_path: &str, filter: &str, operation: &str) -> Result<(), AudioError> {
+    ffmpeg::run(
+        &AudioProcessorConfig::default(),
+        &["-y", "-i", input_path, "-af", filter, output_path],
+        None,
+        operation,
+    )
+}
+
+/// Parses a gain envelope CSV of `time_seconds,gain_db` rows.
+/// Blank lines and a leading header row are skipped; times must be non-negative and strictly increasing.
+pub(crate) fn parse_envelope(contents: &str) -> Result<Vec<(f64, f32)>, AudioError> {
+    let mut points: Vec<(f64, f32)> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split(',').map(str::trim);
+        let (time, gain) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(time), Some(gain), None) => (time, gain),
+            _ => {
+                return Err(AudioError::InvalidParameter(format!(
+                    "envelope line {} must have exactly two columns: {}", index + 1, line
+                )))
+            }
+        };
+        let parsed = time.parse::<f64>().ok().zip(gain.parse::<f32>().ok());
+        let (time, gain) = match parsed {
+            Some(point) => point,
+            // Allow a header row such as "time_seconds,gain_db".
+            None if points.is_empty() && index == 0 => continue,
+            None => {
+                return Err(AudioError::InvalidParameter(format!(
+                    "envelope line {} is not numeric: {}", index + 1, line
+                )))
+            }
+        };
+        if !time.is_finite() || time < 0.0 || !gain.is_finite() {
+            return Err(AudioError::InvalidParameter(format!(
+                "envelope line {} has an invalid time or gain: {}", index + 1, line
+            )));
+        }
+        if let Some(&(previous, _)) = points.last() {
+            if time <= previous {
+                return Err(AudioError::InvalidParameter(format!(
+                    "envelope times must be strictly increasing (line {})", index + 1
+                )));
+            }
+        }
+        points.push((time, gain));
+    }
+    if points.is_empty() {
+        return Err(AudioError::InvalidParameter("envelope contains no points".to_string()));
+    }
+    Ok(points)
+}
+
+/// Builds a time-varying `volume` filter that linearly interpolates gain (in dB) between
+/// envelope points and holds the first/last gain outside the envelope.
+pub(crate) fn envelope_to_filter(points: &[(f64, f32)]) -> String {
+    let (_, last_gain) = points[points.len() - 1];
+    let mut expr = format!("{}", last_gain);
+    for pair in points.windows(2).rev() {
+        let ((t0, g0), (t1, g1)) = (pair[0], pair[1]);
+        expr = format!(
+            "if(lt(t,{t1}),{g0}+({g1}-({g0}))*(t-{t0})/{span},{rest})",
+            t0 = t0, t1 = t1, g0 = g0, g1 = g1, span = t1 - t0, rest = expr
+        );
     }
+    let (first_time, first_gain) = points[0];
+    expr = format!("if(lt(t,{}),{},{})", first_time, first_gain, expr);
+    format!("volume='pow(10,({})/20)':eval=frame", expr)
 }
@@ -0,0 +1,174 @@
+// tags.rs
+
+use crate::config::AudioProcessorConfig;
+use crate::errors::AudioError;
+use crate::processing::db_to_linear;
+use crate::{analysis, ffmpeg, AudioProcessor};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+/// Loudness ReplayGain 2.0 normalizes to, in LUFS.
+const REPLAYGAIN_REFERENCE_LUFS: f32 = -18.0;
+/// Loudness Opus output gain (`R128_TRACK_GAIN`) is relative to, in LUFS.
+const R128_REFERENCE_LUFS: f32 = -23.0;
+
+impl AudioProcessor {
+    /// Measures integrated loudness and true peak, then copies the audio untouched
+    /// (`-c copy`) to `output_path` with ReplayGain tags (`REPLAYGAIN_TRACK_GAIN`,
+    /// `REPLAYGAIN_TRACK_PEAK`) and, for `.opus` outputs, `R128_TRACK_GAIN`.
+    /// Ogg-based outputs carry the tags in the audio stream's comments, as players expect.
+    pub fn write_loudness_tags(&self, output_path: &str) -> Result<Self, AudioError> {
+        let stderr = ffmpeg::analyze(&self.config, &self.file_path, "ebur128=peak=true", "loudness measurement")?;
+        let lufs = analysis::parse_integrated_loudness(&stderr)?;
+        let true_peak_db = analysis::parse_true_peak(&stderr)?;
+
+        let extension = Path::new(output_path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let mut tags = vec![
+            format!("REPLAYGAIN_TRACK_GAIN={:+.2} dB", REPLAYGAIN_REFERENCE_LUFS - lufs),
+            format!("REPLAYGAIN_TRACK_PEAK={:.6}", db_to_linear(true_peak_db)),
+        ];
+        if extension == "opus" {
+            // Q7.8 fixed point, per RFC 7845.
+            tags.push(format!("R128_TRACK_GAIN={}", ((R128_REFERENCE_LUFS - lufs) * 256.0).round() as i32));
+        }
+        let metadata_flag = metadata_flag(output_path);
+        let mut args = vec!["-map", "0", "-c", "copy"];
+        for tag in &tags {
+            args.extend([metadata_flag, tag.as_str()]);
+        }
+        args.push(output_path);
+        self.run_ffmpeg(&[], &args, "write loudness tags")?;
+        println!(
+            "Tagged {} with loudness {:.1} LUFS, true peak {:.1} dBTP -> {}",
+            self.file_path, lufs, true_peak_db, output_path
+        );
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Copies the audio untouched (`-c copy`) to `output_path` with `tags` (e.g. `title`,
+    /// `artist`, `album`) added or replaced; the input's other tags are kept.
+    pub fn set_tags(&self, tags: &HashMap<String, String>, output_path: &str) -> Result<Self, AudioError> {
+        let mut entries: Vec<String> = tags.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        entries.sort();
+        let flag = metadata_flag(output_path);
+        let mut args = vec!["-map", "0", "-c", "copy"];
+        for entry in &entries {
+            args.extend([flag, entry.as_str()]);
+        }
+        args.push(output_path);
+        self.run_ffmpeg(&[], &args, "set tags")?;
+        println!("Set {} tags on {} -> {}", entries.len(), self.file_path, output_path);
+        Ok(self.derived(output_path.to_string()))
+    }
+
+    /// Reads the cue points (markers) embedded in the file, in file order.
+    /// WAV files are parsed directly from their `cue ` chunk; other containers fall back to
+    /// the chapter start times reported by ffprobe. Returns an empty vec when there are none.
+    pub fn read_cue_points(&self) -> Result<Vec<Duration>, AudioError> {
+        let file = File::open(self.config.resolve_path(&self.file_path)).map_err(AudioError::IoError)?;
+        if let Some(points) = read_wav_cue_points(BufReader::new(file))? {
+            return Ok(points);
+        }
+        let chapters = ffmpeg::probe(&self.config, &self.file_path, "chapter=start_time", &[])?;
+        chapters
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.trim().parse::<f64>().map(Duration::from_secs_f64).map_err(|_| {
+                    AudioError::InvalidParameter(format!("could not parse chapter start '{}'", line))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Reads the metadata tags of the file at `path`. Container-level tags are read first;
+/// tags stored on the first audio stream (as in Ogg files) fill in any keys they lack.
+pub fn read_tags(path: &str) -> Result<HashMap<String, String>, AudioError> {
+    let config = AudioProcessorConfig::default();
+    let mut tags: HashMap<String, String> = ffmpeg::probe_tags(&config, path, "format_tags", &[])?.into_iter().collect();
+    for (key, value) in ffmpeg::probe_tags(&config, path, "stream_tags", &["-select_streams", "a:0"])? {
+        tags.entry(key).or_insert(value);
+    }
+    Ok(tags)
+}
+
+/// Returns the `-metadata` flag for tags on `output_path`. Ogg-based formats keep their
+/// comments on the audio stream, where players look for them.
+fn metadata_flag(output_path: &str) -> &'static str {
+    let extension = Path::new(output_path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if matches!(extension.as_str(), "ogg" | "oga" | "opus") { "-metadata:s:a:0" } else { "-metadata" }
+}
+
+/// Parses cue points from a RIFF/WAVE stream, returning `None` if it is not a WAV file.
+/// Chunks other than `fmt ` and `cue ` are skipped without being read.
+fn read_wav_cue_points<R: Read + Seek>(mut reader: R) -> Result<Option<Vec<Duration>>, AudioError> {
+    let mut header = [0u8; 12];
+    if reader.read_exact(&mut header).is_err() || &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Ok(None);
+    }
+    let mut sample_rate = None;
+    let mut offsets = Vec::new();
+    let mut chunk_header = [0u8; 8];
+    while reader.read_exact(&mut chunk_header).is_ok() {
+        let size = u32::from_le_bytes([chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7]]);
+        match &chunk_header[0..4] {
+            b"fmt " | b"cue " => {
+                let mut data = vec![0u8; size as usize];
+                reader.read_exact(&mut data).map_err(AudioError::IoError)?;
+                if &chunk_header[0..4] == b"fmt " {
+                    if data.len() < 8 {
+                        return Err(AudioError::InvalidParameter("WAV fmt chunk is truncated".to_string()));
+                    }
+                    sample_rate = Some(u32::from_le_bytes([data[4], data[5], data[6], data[7]]));
+                } else {
+                    offsets = parse_cue_chunk(&data)?;
+                }
+                if size % 2 == 1 {
+                    reader.seek(SeekFrom::Current(1)).map_err(AudioError::IoError)?;
+                }
+            }
+            // Chunks are padded to an even length.
+            _ => {
+                reader.seek(SeekFrom::Current(i64::from(size + size % 2))).map_err(AudioError::IoError)?;
+            }
+        }
+    }
+    if offsets.is_empty() {
+        return Ok(Some(Vec::new()));
+    }
+    let sample_rate = match sample_rate {
+        Some(rate) if rate > 0 => rate,
+        _ => return Err(AudioError::InvalidParameter("WAV file has cue points but no valid fmt chunk".to_string())),
+    };
+    Ok(Some(
+        offsets
+            .into_iter()
+            .map(|offset| Duration::from_secs_f64(f64::from(offset) / f64::from(sample_rate)))
+            .collect(),
+    ))
+}
+
+/// Returns the sample offset of every cue point in a `cue ` chunk body.
+/// Each point is 24 bytes: id, position, data chunk id, chunk start, block start, sample offset.
+fn parse_cue_chunk(data: &[u8]) -> Result<Vec<u32>, AudioError> {
+    let truncated = || AudioError::InvalidParameter("WAV cue chunk is truncated".to_string());
+    let count = data.get(0..4).ok_or_else(truncated)?;
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    (0..count)
+        .map(|index| {
+            let start = 4 + index * 24 + 20;
+            let offset = data.get(start..start + 4).ok_or_else(truncated)?;
+            Ok(u32::from_le_bytes([offset[0], offset[1], offset[2], offset[3]]))
+        })
+        .collect()
+}
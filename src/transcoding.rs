@@ -1,5 +1,8 @@
+use crate::errors::AudioError;
+use crate::ffmpeg;
+
 /// Supported audio formats for transcoding.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AudioFormat {
     Mp3,
     Wav,
@@ -7,3 +10,380 @@ pub enum AudioFormat {
     Ogg,
     // Add more formats as needed.
 }
+
+/// Constant bitrates (kbps) supported by MPEG-1 Layer III.
+const MP3_BITRATES: [u32; 14] = [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+
+impl AudioFormat {
+    /// Returns the FFmpeg muxer/demuxer name for this format (as passed to `-f`).
+    pub fn format_name(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Ogg => "ogg",
+        }
+    }
+
+    /// Returns the file extension for this format, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Ogg => "ogg",
+        }
+    }
+
+    /// Returns the FFmpeg encoder used when this format is written.
+    pub fn default_codec(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "libmp3lame",
+            AudioFormat::Wav => "pcm_s16le",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Ogg => "libvorbis",
+        }
+    }
+
+    /// Returns the format whose extension `path` has (compared case-insensitively), if any.
+    pub(crate) fn for_path(path: &str) -> Option<AudioFormat> {
+        let extension = std::path::Path::new(path).extension()?;
+        [AudioFormat::Mp3, AudioFormat::Wav, AudioFormat::Flac, AudioFormat::Ogg]
+            .into_iter()
+            .find(|format| extension.eq_ignore_ascii_case(format.extension()))
+    }
+
+    /// Checks `output_path` against this format: a path without an extension gets this
+    /// format's appended, and one with a different extension (compared case-insensitively)
+    /// fails with `InvalidParameter`. Returns the path to write.
+    pub(crate) fn output_path(&self, output_path: &str) -> Result<String, AudioError> {
+        match std::path::Path::new(output_path).extension() {
+            None => Ok(format!("{}.{}", output_path, self.extension())),
+            Some(ext) if ext.eq_ignore_ascii_case(self.extension()) => Ok(output_path.to_string()),
+            Some(ext) => Err(AudioError::InvalidParameter(format!(
+                "output path {} has extension .{}, but {:?} output needs .{}",
+                output_path, ext.to_string_lossy(), self, self.extension()
+            ))),
+        }
+    }
+
+    /// Returns the valid `(min, max)` bitrate range in kbps for lossy formats,
+    /// or `None` for lossless formats whose size can't be targeted by bitrate.
+    pub fn bitrate_range(&self) -> Option<(u32, u32)> {
+        match self {
+            AudioFormat::Mp3 => Some((32, 320)),
+            AudioFormat::Ogg => Some((45, 500)),
+            AudioFormat::Wav | AudioFormat::Flac => None,
+        }
+    }
+
+    /// Returns the approximate bytes per second this format's default encoder writes for
+    /// audio at `sample_rate` with `channels`.
+    pub(crate) fn estimated_byte_rate(&self, sample_rate: u32, channels: u8) -> f64 {
+        let pcm = f64::from(sample_rate) * f64::from(channels) * 2.0;
+        match self {
+            AudioFormat::Wav => pcm,
+            // FLAC typically compresses music to 50-70% of its PCM size.
+            AudioFormat::Flac => pcm * 0.6,
+            // libmp3lame defaults to 128 kbps; libvorbis' default quality averages ~112 kbps.
+            AudioFormat::Mp3 => 128_000.0 / 8.0,
+            AudioFormat::Ogg => 112_000.0 / 8.0,
+        }
+    }
+
+    /// Returns the highest bitrate (kbps) the encoder will honour that does not exceed `kbps`,
+    /// or `None` if `kbps` is below the format's minimum.
+    pub(crate) fn bitrate_at_most(&self, kbps: u32) -> Option<u32> {
+        let (min, max) = self.bitrate_range()?;
+        match self {
+            // MP3 encoders round to the nearest standard rate, which may be above the budget.
+            AudioFormat::Mp3 => MP3_BITRATES.iter().rev().copied().find(|&rate| rate <= kbps),
+            _ if kbps < min => None,
+            _ => Some(kbps.min(max)),
+        }
+    }
+}
+
+/// An FFmpeg audio encoder, chosen independently of the container it is written to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Codec {
+    LibMp3Lame,
+    Aac,
+    Flac,
+    LibVorbis,
+    LibOpus,
+    PcmS16le,
+    Alac,
+    /// Any other encoder, by its FFmpeg name; its container compatibility is not checked.
+    Other(String),
+}
+
+impl Codec {
+    /// Returns the encoder name as passed to `-c:a`.
+    pub fn as_ffmpeg_arg(&self) -> &str {
+        match self {
+            Codec::LibMp3Lame => "libmp3lame",
+            Codec::Aac => "aac",
+            Codec::Flac => "flac",
+            Codec::LibVorbis => "libvorbis",
+            Codec::LibOpus => "libopus",
+            Codec::PcmS16le => "pcm_s16le",
+            Codec::Alac => "alac",
+            Codec::Other(name) => name,
+        }
+    }
+
+    /// Checks that this codec can be written to `container`, an FFmpeg muxer name or file
+    /// extension such as `mp3` or `m4a`. Fails with `InvalidParameter` for impossible
+    /// combinations like FLAC in MP3; containers not listed here (e.g. Matroska) and
+    /// `Codec::Other` are not checked.
+    pub(crate) fn check_container(&self, container: &str) -> Result<(), AudioError> {
+        let allowed: &[Codec] = match container.to_ascii_lowercase().as_str() {
+            "mp3" => &[Codec::LibMp3Lame],
+            "wav" => &[Codec::PcmS16le, Codec::LibMp3Lame],
+            "flac" => &[Codec::Flac],
+            "ogg" | "oga" => &[Codec::LibVorbis, Codec::LibOpus, Codec::Flac],
+            "opus" => &[Codec::LibOpus],
+            "aac" | "adts" => &[Codec::Aac],
+            "m4a" | "ipod" => &[Codec::Aac, Codec::Alac],
+            "mp4" | "mov" => &[Codec::Aac, Codec::Alac, Codec::Flac, Codec::LibOpus, Codec::LibMp3Lame, Codec::PcmS16le],
+            _ => return Ok(()),
+        };
+        if matches!(self, Codec::Other(_)) || allowed.contains(self) {
+            Ok(())
+        } else {
+            Err(AudioError::InvalidParameter(format!(
+                "codec {} cannot be written to a {} container", self.as_ffmpeg_arg(), container
+            )))
+        }
+    }
+}
+
+impl From<&str> for Codec {
+    /// Maps an FFmpeg encoder name to its variant, or `Codec::Other` if it has none.
+    fn from(name: &str) -> Self {
+        match name {
+            "libmp3lame" => Codec::LibMp3Lame,
+            "aac" => Codec::Aac,
+            "flac" => Codec::Flac,
+            "libvorbis" => Codec::LibVorbis,
+            "libopus" => Codec::LibOpus,
+            "pcm_s16le" => Codec::PcmS16le,
+            "alac" => Codec::Alac,
+            other => Codec::Other(other.to_string()),
+        }
+    }
+}
+
+/// PCM sample encoding for WAV output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavBitDepth {
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+}
+
+impl WavBitDepth {
+    /// Picks the depth for a bit count, e.g. from a user setting. Only 16, 24 and 32-bit
+    /// integer and 32-bit float PCM are supported.
+    pub fn from_bits(bits: u8, float: bool) -> Result<Self, AudioError> {
+        match (bits, float) {
+            (16, false) => Ok(WavBitDepth::Int16),
+            (24, false) => Ok(WavBitDepth::Int24),
+            (32, false) => Ok(WavBitDepth::Int32),
+            (32, true) => Ok(WavBitDepth::Float32),
+            _ => Err(AudioError::InvalidParameter(format!(
+                "unsupported WAV bit depth: {}-bit {}", bits, if float { "float" } else { "integer" }
+            ))),
+        }
+    }
+
+    /// Returns the FFmpeg PCM encoder for this depth.
+    pub fn codec_name(&self) -> &'static str {
+        match self {
+            WavBitDepth::Int16 => "pcm_s16le",
+            WavBitDepth::Int24 => "pcm_s24le",
+            WavBitDepth::Int32 => "pcm_s32le",
+            WavBitDepth::Float32 => "pcm_f32le",
+        }
+    }
+}
+
+/// A quality goal for `AudioProcessor::transcode_for_quality`, which picks the format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityTarget {
+    /// FLAC: bit-exact with the source.
+    Lossless,
+    /// MP3 at 320 kbps: transparent for most listeners and plays everywhere.
+    HighLossy,
+    /// Opus at 96 kbps: small files that still sound good for music and speech.
+    SmallSize,
+}
+
+impl QualityTarget {
+    /// Returns the file extension (without the dot) used for this target.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            QualityTarget::Lossless => "flac",
+            QualityTarget::HighLossy => "mp3",
+            QualityTarget::SmallSize => "opus",
+        }
+    }
+
+    /// Returns the encoder settings for this target.
+    pub(crate) fn options(&self) -> TranscodeOptions {
+        match self {
+            QualityTarget::Lossless => TranscodeOptions::new().codec("flac"),
+            QualityTarget::HighLossy => TranscodeOptions::new().codec("libmp3lame").bitrate(BitrateMode::Constant(320)),
+            QualityTarget::SmallSize => TranscodeOptions::new().codec("libopus").bitrate(BitrateMode::Constant(96)),
+        }
+    }
+}
+
+/// How the encoder's bitrate is chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitrateMode {
+    /// Constant bitrate in kbps (`-b:a {n}k`).
+    Constant(u32),
+    /// Encoder-specific variable quality scale (`-q:a {n}`), e.g. 0–9 for LAME, lower is better.
+    Variable(u8),
+}
+
+/// Encoding settings for `AudioProcessor::transcode_with`.
+/// Every field is optional; unset fields are left to FFmpeg's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct TranscodeOptions {
+    /// Encoder (`-c:a`), checked against the container before encoding.
+    pub codec: Option<Codec>,
+    pub bitrate: Option<BitrateMode>,
+    /// Output sample rate in Hz (`-ar`).
+    pub sample_rate: Option<u32>,
+    /// Output channel count (`-ac`).
+    pub channels: Option<u16>,
+    /// Output sample format, e.g. `s16` or `fltp` (`-sample_fmt`).
+    pub sample_format: Option<String>,
+    /// Container to write (`-f`); by default it is inferred from the output extension.
+    pub container: Option<AudioFormat>,
+    /// Copy the input's global metadata to the output; when false, metadata is stripped.
+    /// Unset, the config's `preserve_metadata` applies.
+    pub preserve_metadata: Option<bool>,
+}
+
+impl TranscodeOptions {
+    /// Starts from FFmpeg's defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the encoder, as a `Codec` or an FFmpeg encoder name such as `"libmp3lame"`.
+    pub fn codec(mut self, codec: impl Into<Codec>) -> Self {
+        self.codec = Some(codec.into());
+        self
+    }
+
+    /// Writes PCM at the given depth; sets the codec, so use it with WAV output.
+    pub fn wav_bit_depth(self, depth: WavBitDepth) -> Self {
+        self.codec(depth.codec_name())
+    }
+
+    pub fn bitrate(mut self, bitrate: BitrateMode) -> Self {
+        self.bitrate = Some(bitrate);
+        self
+    }
+
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    pub fn channels(mut self, channels: u16) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    pub fn sample_format(mut self, sample_format: &str) -> Self {
+        self.sample_format = Some(sample_format.to_string());
+        self
+    }
+
+    pub fn container(mut self, container: AudioFormat) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    pub fn preserve_metadata(mut self, preserve: bool) -> Self {
+        self.preserve_metadata = Some(preserve);
+        self
+    }
+
+    /// Checks the codec, if any, against the container: `container` when set, otherwise
+    /// the extension of `output_path`.
+    pub(crate) fn check_codec(&self, output_path: &str) -> Result<(), AudioError> {
+        let Some(codec) = &self.codec else {
+            return Ok(());
+        };
+        match self.container {
+            Some(container) => codec.check_container(container.format_name()),
+            None => match std::path::Path::new(output_path).extension() {
+                Some(ext) => codec.check_container(&ext.to_string_lossy()),
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Builds the FFmpeg output arguments for these options.
+    pub(crate) fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(codec) = &self.codec {
+            args.extend(["-c:a".to_string(), codec.as_ffmpeg_arg().to_string()]);
+        }
+        match self.bitrate {
+            Some(BitrateMode::Constant(kbps)) => args.extend(["-b:a".to_string(), format!("{}k", kbps)]),
+            Some(BitrateMode::Variable(quality)) => args.extend(["-q:a".to_string(), quality.to_string()]),
+            None => {}
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            args.extend(["-ar".to_string(), sample_rate.to_string()]);
+        }
+        if let Some(channels) = self.channels {
+            args.extend(["-ac".to_string(), channels.to_string()]);
+        }
+        if let Some(sample_format) = &self.sample_format {
+            args.extend(["-sample_fmt".to_string(), sample_format.clone()]);
+        }
+        if let Some(preserve) = self.preserve_metadata {
+            let metadata_source = if preserve { "0" } else { "-1" };
+            args.extend(["-map_metadata".to_string(), metadata_source.to_string()]);
+        }
+        if let Some(container) = self.container {
+            args.extend(["-f".to_string(), container.format_name().to_string()]);
+        }
+        args
+    }
+}
+
+/// A transcode running in the background, from `AudioProcessor::transcode_spawn`.
+/// `cancel` may be called from another thread while one is blocked in `wait`, e.g. to
+/// back a stop button. Dropping a handle that was never waited on cancels it.
+pub struct TranscodeHandle {
+    job: ffmpeg::Job,
+}
+
+impl TranscodeHandle {
+    pub(crate) fn new(job: ffmpeg::Job) -> Self {
+        TranscodeHandle { job }
+    }
+
+    /// Waits for the transcode to finish. Fails with `AudioError::Cancelled` if it was
+    /// cancelled first.
+    pub fn wait(&self) -> Result<(), AudioError> {
+        self.job.wait()
+    }
+
+    /// Stops the transcode, killing ffmpeg and removing the partly written output.
+    /// A later `wait` reports `AudioError::Cancelled`.
+    pub fn cancel(&self) -> Result<(), AudioError> {
+        self.job.cancel()
+    }
+}
@@ -0,0 +1,129 @@
+// watch.rs
+
+use crate::errors::AudioError;
+use crate::AudioProcessor;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a file must go without events, at an unchanged size, before it is processed.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// A running `watch_directory` job. Watching stops when `stop` is called or the handle is dropped.
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl WatchHandle {
+    /// Stops watching and waits for any operation in progress to finish.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// A file seen by the watcher that hasn't settled yet.
+struct Pending {
+    size: Option<u64>,
+    last_event: Instant,
+}
+
+impl AudioProcessor {
+    /// Watches `dir` and runs `op` on each new file whose extension matches `ext_filter`
+    /// (e.g. `"wav"`, case-insensitive), on a background thread.
+    /// A file is processed once events for it have stopped and its size is stable, so
+    /// files still being copied in are skipped until complete. Files `op` writes back
+    /// into `dir` are not processed again. Failures are logged and don't stop the watch.
+    pub fn watch_directory(
+        dir: &str,
+        ext_filter: &str,
+        op: impl Fn(AudioProcessor) -> Result<AudioProcessor, AudioError> + Send + 'static,
+    ) -> Result<WatchHandle, AudioError> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+        watcher.watch(Path::new(dir), RecursiveMode::NonRecursive).map_err(watch_error)?;
+        println!("Watching {} for .{} files", dir, ext_filter);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker_stop = Arc::clone(&stop);
+        let extension = ext_filter.trim_start_matches('.').to_ascii_lowercase();
+        let worker = thread::spawn(move || {
+            let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+            let mut seen: HashSet<PathBuf> = HashSet::new();
+            while !worker_stop.load(Ordering::SeqCst) {
+                match events.recv_timeout(Duration::from_millis(100)) {
+                    Ok(Ok(event)) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                        for path in event.paths {
+                            let matches = path
+                                .extension()
+                                .is_some_and(|ext| ext.to_string_lossy().to_ascii_lowercase() == extension);
+                            if matches && !seen.contains(&path) {
+                                let entry = pending.entry(path).or_insert(Pending { size: None, last_event: Instant::now() });
+                                entry.last_event = Instant::now();
+                            }
+                        }
+                    }
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => log::warn!("directory watch error: {}", e),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+
+                let settled: Vec<PathBuf> = pending
+                    .iter_mut()
+                    .filter_map(|(path, entry)| {
+                        if entry.last_event.elapsed() < SETTLE_TIME {
+                            return None;
+                        }
+                        let size = std::fs::metadata(path).ok().map(|metadata| metadata.len());
+                        if size.is_some() && size == entry.size {
+                            Some(path.clone())
+                        } else {
+                            // Still growing (or gone); check again once it has settled.
+                            entry.size = size;
+                            entry.last_event = Instant::now();
+                            None
+                        }
+                    })
+                    .collect();
+                for path in settled {
+                    pending.remove(&path);
+                    seen.insert(path.clone());
+                    let result = AudioProcessor::new(&path.to_string_lossy()).and_then(&op);
+                    match result {
+                        Ok(output) => {
+                            seen.insert(PathBuf::from(&output.file_path));
+                        }
+                        Err(e) => log::warn!("watched operation failed for {}: {:?}", path.display(), e),
+                    }
+                }
+            }
+        });
+        Ok(WatchHandle { stop, worker: Some(worker), _watcher: watcher })
+    }
+}
+
+fn watch_error(e: notify::Error) -> AudioError {
+    match e.kind {
+        notify::ErrorKind::Io(io) => AudioError::IoError(io),
+        kind => AudioError::InvalidParameter(format!("could not watch directory: {:?}", kind)),
+    }
+}
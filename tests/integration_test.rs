@@ -1,7 +1,15 @@
 use audio_processor::{
     AudioProcessor,
-    transcoding::AudioFormat,
-    processing::AudioEffect,
+    io::{FileInfo, NoiseColor, generate_click_track, generate_noise, input_is_accessible, read_wav_header, write_wav},
+    config::{AudioProcessorConfig, ClippingPolicy, MergeMismatchPolicy},
+    errors::AudioError,
+    operation::Operation,
+    tags::read_tags,
+    transcoding::{AudioFormat, BitrateMode, Codec, QualityTarget, TranscodeOptions, WavBitDepth},
+    processing::{
+        AudioEffect, CompressorParams, NormalizeMode, atempo_chain, db_to_linear, fade_in, linear_to_db, set_volume,
+        validate_filtergraph,
+    },
 };
 
 use std::fs;
@@ -19,7 +27,7 @@ fn setup_test_file() -> String {
         fs::create_dir_all(test_dir).expect("Failed to create test_data directory");
         // Generate a 5-second silent audio file.
         let status = std::process::Command::new("ffmpeg")
-            .args(&[
+            .args([
                 "-f", "lavfi",
                 "-i", "anullsrc=r=44100:cl=stereo",
                 "-t", "5",
@@ -33,6 +41,49 @@ fn setup_test_file() -> String {
     file_path
 }
 
+/// Helper function to ensure that a 5-second 440 Hz stereo tone exists.
+/// Used by tests that need a non-silent signal.
+fn setup_tone_file() -> String {
+    let test_dir = "tests/test_data";
+    let file_path = format!("{}/tone.wav", test_dir);
+    if !Path::new(&file_path).exists() {
+        fs::create_dir_all(test_dir).expect("Failed to create test_data directory");
+        let status = std::process::Command::new("ffmpeg")
+            .args([
+                "-f", "lavfi",
+                "-i", "sine=frequency=440:sample_rate=44100",
+                "-t", "5",
+                "-ac", "2",
+                &file_path,
+                "-y"
+            ])
+            .status()
+            .expect("Failed to generate test tone file using ffmpeg");
+        assert!(status.success(), "ffmpeg failed to create tone file");
+    }
+    file_path
+}
+
+/// Writes an executable shell script named `name` into `dir` to stand in for ffmpeg or
+/// ffprobe, and returns its path. `script` is the body, without the `#!/bin/sh` line.
+#[cfg(unix)]
+fn fake_tool(dir: &Path, name: &str, script: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{}", script)).expect("Failed to write fake tool");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).expect("Failed to mark fake tool executable");
+    path
+}
+
+/// Writes a placeholder input file named `name` into `dir`, for tests whose ffmpeg is
+/// faked or never runs, and returns its path.
+fn fake_input(dir: &Path, name: &str) -> std::path::PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, b"placeholder").expect("Failed to write input");
+    path
+}
+
 #[test]
 fn test_seek() {
     let file = setup_test_file();
@@ -52,6 +103,16 @@ fn test_trim() {
     let _ = fs::remove_file(&trimmed_processor.file_path);
 }
 
+#[test]
+fn test_trim_sub_second_bounds() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let trimmed = processor.trim(Duration::from_millis(1500), Duration::from_millis(3000)).expect("Trim failed");
+    let length = trimmed.duration().expect("Duration failed").as_secs_f64();
+    assert!((length - 1.5).abs() < 0.1, "trimmed to {}s instead of 1.5s", length);
+    let _ = fs::remove_file(&trimmed.file_path);
+}
+
 #[test]
 fn test_transcode() {
     let file = setup_test_file();
@@ -130,3 +191,2401 @@ fn test_overlay() {
     assert!(Path::new(&overlayed_processor.file_path).exists());
     let _ = fs::remove_file(&overlayed_processor.file_path);
 }
+
+#[test]
+fn test_to_hls() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output_dir = "tests/test_data/hls";
+    let playlist = processor.to_hls(Duration::from_secs(2), output_dir).expect("HLS packaging failed");
+    assert!(Path::new(&playlist).exists());
+    let segments = fs::read_dir(output_dir)
+        .expect("Failed to read HLS output directory")
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "ts"))
+        .count();
+    assert!(segments >= 1, "expected at least one HLS segment");
+    let _ = fs::remove_dir_all(output_dir);
+}
+
+#[test]
+fn test_is_bit_identical() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let flac_path = "tests/test_data/roundtrip.flac";
+    let wav_path = "tests/test_data/roundtrip.wav";
+    processor.transcode(AudioFormat::Flac, flac_path).expect("Transcode to FLAC failed");
+    AudioProcessor::new(flac_path)
+        .expect("Failed to create FLAC processor")
+        .transcode(AudioFormat::Wav, wav_path)
+        .expect("Transcode back to WAV failed");
+    let roundtrip = AudioProcessor::new(wav_path).expect("Failed to create round-trip processor");
+    assert!(processor.is_bit_identical(&roundtrip).expect("Comparison failed"));
+
+    let louder = processor.adjust_volume(1.5).expect("Adjust volume failed");
+    assert!(!processor.is_bit_identical(&louder).expect("Comparison failed"));
+
+    let resampled = processor.resample(22050).expect("Resample failed");
+    assert!(!processor.is_bit_identical(&resampled).expect("Comparison failed"));
+
+    let _ = fs::remove_file(flac_path);
+    let _ = fs::remove_file(wav_path);
+    let _ = fs::remove_file(&louder.file_path);
+    let _ = fs::remove_file(&resampled.file_path);
+}
+
+#[test]
+fn test_speed_factor_rejects_bad_range() {
+    let inverted = AudioProcessorConfig { min_speed: 4.0, max_speed: 0.5, ..AudioProcessorConfig::default() };
+    assert!(matches!(inverted.speed_factor(50.0), Err(AudioError::InvalidParameter(_))));
+    let unbounded = AudioProcessorConfig { max_speed: f32::NAN, ..AudioProcessorConfig::default() };
+    assert!(matches!(unbounded.speed_factor(2.0), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_change_speed_clamping() {
+    let config = AudioProcessorConfig::default();
+    assert_eq!(config.speed_factor(50.0).expect("Clamping failed"), config.max_speed);
+
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let clamped = processor.change_speed(50.0).expect("Clamped change speed failed");
+    assert!(Path::new(&clamped.file_path).exists());
+    let _ = fs::remove_file(&clamped.file_path);
+
+    let strict = AudioProcessorConfig { clamp_speed: false, ..AudioProcessorConfig::default() };
+    let processor = AudioProcessor::with_config(&file, strict).expect("Failed to create processor");
+    assert!(matches!(processor.change_speed(50.0), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_reverse_chunked() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let reversed = processor.reverse_chunked(Duration::from_secs(1)).expect("Chunked reverse failed");
+    let original = processor.duration().expect("Failed to probe input duration");
+    let result = reversed.duration().expect("Failed to probe output duration");
+    assert!((original.as_secs_f64() - result.as_secs_f64()).abs() < 0.1);
+    let _ = fs::remove_file(&reversed.file_path);
+}
+
+#[test]
+fn test_from_stdin() {
+    let file = setup_test_file();
+    let bytes = fs::read(&file).expect("Failed to read test file");
+    let processor = AudioProcessor::from_stdin(AudioFormat::Wav, std::io::Cursor::new(bytes));
+    let output_path = "tests/test_data/from_stdin.mp3";
+    processor.transcode(AudioFormat::Mp3, output_path).expect("Transcode from stdin failed");
+    assert!(Path::new(output_path).exists());
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_apply_envelope_file() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let csv_path = "tests/test_data/envelope.csv";
+    fs::write(csv_path, "time_seconds,gain_db\n0,0\n2,-6\n4,-30\n").expect("Failed to write envelope");
+    let enveloped = processor.apply_envelope_file(csv_path).expect("Apply envelope failed");
+    assert!(Path::new(&enveloped.file_path).exists());
+
+    fs::write(csv_path, "0,0\n3,-6\n2,-30\n").expect("Failed to write envelope");
+    assert!(matches!(processor.apply_envelope_file(csv_path), Err(AudioError::InvalidParameter(_))));
+
+    let _ = fs::remove_file(csv_path);
+    let _ = fs::remove_file(&enveloped.file_path);
+}
+
+#[test]
+fn test_normalize_with_dc_removal() {
+    fs::create_dir_all("tests/test_data").expect("Failed to create test_data directory");
+    let biased_path = "tests/test_data/dc_biased.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args([
+            "-f", "lavfi",
+            "-i", "aevalsrc=0.2+0.3*sin(2*PI*440*t):s=44100:d=5",
+            biased_path,
+            "-y"
+        ])
+        .status()
+        .expect("Failed to generate DC-biased file using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create DC-biased file");
+
+    let processor = AudioProcessor::new(biased_path).expect("Failed to create processor");
+    assert!(processor.level_stats().expect("Level stats failed").dc_offset > 0.1);
+    let normalized = processor.normalize_with_dc_removal(-16.0).expect("Normalize with DC removal failed");
+    let stats = normalized.level_stats().expect("Level stats failed");
+    assert!(stats.dc_offset.abs() < 0.01, "DC offset {} not removed", stats.dc_offset);
+    let loudness = normalized.integrated_loudness().expect("Loudness measurement failed");
+    assert!((loudness + 16.0).abs() < 1.5, "loudness {} not near -16 LUFS", loudness);
+    let _ = fs::remove_file(biased_path);
+    let _ = fs::remove_file(&normalized.file_path);
+}
+
+#[test]
+fn test_channels() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    assert_eq!(processor.channels().expect("Channel probe failed"), 2);
+}
+
+#[test]
+fn test_sample_rate() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    assert_eq!(processor.sample_rate().expect("Sample rate probe failed"), 44100);
+}
+
+#[test]
+fn test_normalize_album() {
+    let file = setup_tone_file();
+    let quiet_path = "tests/test_data/tone_quiet.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-i", &file, "-af", "volume=-9dB", quiet_path, "-y"])
+        .status()
+        .expect("Failed to generate quiet tone using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create quiet tone");
+    let loud = AudioProcessor::new(&file).expect("Failed to create processor");
+    let quiet = AudioProcessor::new(quiet_path).expect("Failed to create quiet processor");
+    let before = loud.integrated_loudness().unwrap() - quiet.integrated_loudness().unwrap();
+
+    let output_dir = "tests/test_data/album";
+    let outputs = AudioProcessor::normalize_album(&[&file, quiet_path], -16.0, output_dir)
+        .expect("Album normalization failed");
+    assert_eq!(outputs.len(), 2);
+    let after = outputs[0].integrated_loudness().unwrap() - outputs[1].integrated_loudness().unwrap();
+    assert!((before - after).abs() < 0.5, "relative loudness changed from {} to {}", before, after);
+
+    let _ = fs::remove_file(quiet_path);
+    let _ = fs::remove_dir_all(output_dir);
+}
+
+#[test]
+fn test_atomic_output() {
+    let file = setup_test_file();
+    let config = AudioProcessorConfig { atomic_output: true, ..AudioProcessorConfig::default() };
+    let processor = AudioProcessor::with_config(&file, config).expect("Failed to create processor");
+    let output_dir = "tests/test_data/atomic";
+    fs::create_dir_all(output_dir).expect("Failed to create output directory");
+    let output_path = format!("{}/atomic.mp3", output_dir);
+    processor.transcode(AudioFormat::Mp3, &output_path).expect("Atomic transcode failed");
+    assert!(Path::new(&output_path).exists());
+    // Only the renamed final file remains; the staging file never outlives the run.
+    let entries: Vec<_> = fs::read_dir(output_dir)
+        .expect("Failed to read output directory")
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(entries, vec!["atomic.mp3".to_string()]);
+    let _ = fs::remove_dir_all(output_dir);
+}
+
+#[test]
+fn test_atomic_output_segments() {
+    let file = setup_tone_file();
+    let config = AudioProcessorConfig { atomic_output: true, ..AudioProcessorConfig::default() };
+    let processor = AudioProcessor::with_config(&file, config).expect("Failed to create processor");
+    let list = |dir: &Path| -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .expect("Failed to read output directory")
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    };
+
+    let split_dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let segments = processor
+        .split_into_segments(Duration::from_secs(2), split_dir.path().to_str().unwrap())
+        .expect("Atomic split failed");
+    assert_eq!(segments.len(), 3);
+    assert_eq!(list(split_dir.path()), vec!["tone_000.wav", "tone_001.wav", "tone_002.wav"]);
+
+    let hls_dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let playlist = processor
+        .to_hls(Duration::from_secs(2), hls_dir.path().to_str().unwrap())
+        .expect("Atomic HLS packaging failed");
+    assert!(Path::new(&playlist).exists());
+    let names = list(hls_dir.path());
+    assert!(names.iter().any(|name| name.starts_with("segment_") && name.ends_with(".ts")), "{:?}", names);
+    assert!(names.iter().all(|name| !name.starts_with(".partial-")), "staging files left behind: {:?}", names);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_atomic_output_appears_only_after_success() {
+    // A stand-in ffmpeg that records whether the final path exists while it is still
+    // writing, then exits with $FAKE_EXIT.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\nfor last; do :; done\n\
+         if [ -e \"$FINAL_OUTPUT\" ]; then echo present; else echo absent; fi > \"$CHECK_LOG\"\n\
+         echo rendered > \"$last\"\nexit \"${FAKE_EXIT:-0}\"\n",
+    );
+    let input = fake_input(dir.path(), "input.wav");
+    let out_dir = dir.path().join("out");
+    fs::create_dir(&out_dir).expect("Failed to create output directory");
+    let check_log = dir.path().join("check.log");
+
+    for (exit_code, succeeds) in [("0", true), ("1", false)] {
+        let output = out_dir.join(format!("reversed_{}.wav", exit_code));
+        let mut config = AudioProcessorConfig {
+            atomic_output: true,
+            ffmpeg_path: Some(fake_ffmpeg.clone()),
+            ..AudioProcessorConfig::default()
+        };
+        config.env.insert("FINAL_OUTPUT".to_string(), output.to_string_lossy().into_owned());
+        config.env.insert("CHECK_LOG".to_string(), check_log.to_string_lossy().into_owned());
+        config.env.insert("FAKE_EXIT".to_string(), exit_code.to_string());
+        let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+
+        let result = processor.reverse_to(output.to_str().unwrap());
+        assert_eq!(result.is_ok(), succeeds, "{:?}", result);
+        assert_eq!(fs::read_to_string(&check_log).unwrap(), "absent\n", "final path existed while ffmpeg ran");
+        assert_eq!(output.exists(), succeeds);
+        if succeeds {
+            assert_eq!(fs::read_to_string(&output).unwrap(), "rendered\n");
+        }
+        let leftovers: Vec<_> = fs::read_dir(&out_dir)
+            .expect("Failed to read output directory")
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(".partial-"))
+            .collect();
+        assert!(leftovers.is_empty(), "staging file left behind: {:?}", leftovers);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failed_multi_output_run_removes_every_output() {
+    // A stand-in ffmpeg that writes to every output it is handed, then fails.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\n\
+         [ \"$2\" = -encoders ] && printf ' ------\\n A....D libmp3lame  MP3\\n' && exit 0\n\
+         for arg; do case \"$arg\" in *_ch*.wav) echo partial > \"$arg\" ;; esac; done\n\
+         [ -n \"$TEE_OUTPUTS\" ] && for path in $TEE_OUTPUTS; do echo partial > \"$path\"; done\n\
+         exit 1\n",
+    );
+    let fake_ffprobe = fake_tool(dir.path(), "ffprobe", "echo 2\n");
+    let input = fake_input(dir.path(), "input.wav");
+    let out_dir = dir.path().join("out");
+    let first = dir.path().join("first.mp3");
+    let second = dir.path().join("second.mp3");
+    let mut config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg),
+        ffprobe_path: Some(fake_ffprobe),
+        ..AudioProcessorConfig::default()
+    };
+    config.env.insert("TEE_OUTPUTS".to_string(), format!("{} {}", first.display(), second.display()));
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+
+    assert!(processor.split_channels(out_dir.to_str().unwrap()).is_err());
+    let leftovers: Vec<_> = fs::read_dir(&out_dir).expect("Failed to read output directory").filter_map(Result::ok).collect();
+    assert!(leftovers.is_empty(), "channel files left behind: {:?}", leftovers);
+
+    let outputs = [
+        (AudioFormat::Mp3, first.to_string_lossy().into_owned()),
+        (AudioFormat::Mp3, second.to_string_lossy().into_owned()),
+    ];
+    assert!(processor.tee(&outputs).is_err());
+    assert!(!first.exists() && !second.exists(), "tee outputs left behind");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dropping_progress_iter_stops_ffmpeg() {
+    // A stand-in ffmpeg that starts writing, reports some progress, then hangs.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\n\
+         [ \"$2\" = -encoders ] && printf ' ------\\n A....D libmp3lame  MP3\\n' && exit 0\n\
+         for last; do :; done\necho partial > \"$last\"\necho out_time_ms=1000000\nexec sleep 30\n",
+    );
+    let fake_ffprobe = fake_tool(dir.path(), "ffprobe", "echo 5.0\n");
+    let input = fake_input(dir.path(), "input.wav");
+    let output = dir.path().join("abandoned.mp3");
+    let config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg),
+        ffprobe_path: Some(fake_ffprobe),
+        ..AudioProcessorConfig::default()
+    };
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+
+    let started = std::time::Instant::now();
+    let mut progress = processor.transcode_progress_iter(AudioFormat::Mp3, output.to_str().unwrap());
+    let first = progress.next().expect("No progress reported").expect("Progress failed");
+    assert!(first > 0.0 && first < 1.0, "unexpected progress {}", first);
+    drop(progress);
+    assert!(!output.exists(), "partial output left behind");
+    assert!(started.elapsed() < Duration::from_secs(10), "dropping the iterator waited for ffmpeg to finish");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_normalize_album_rejects_duplicate_names() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(dir.path(), "ffmpeg", "exit 0\n");
+    let (disc_one, disc_two) = (dir.path().join("disc1"), dir.path().join("disc2"));
+    fs::create_dir(&disc_one).expect("Failed to create directory");
+    fs::create_dir(&disc_two).expect("Failed to create directory");
+    let first = fake_input(&disc_one, "track.wav");
+    let second = fake_input(&disc_two, "track.wav");
+    let config = AudioProcessorConfig { ffmpeg_path: Some(fake_ffmpeg), ..AudioProcessorConfig::default() };
+
+    let output_dir = dir.path().join("album");
+    let result = AudioProcessor::normalize_album_with_config(
+        &[first.to_str().unwrap(), second.to_str().unwrap()],
+        -14.0,
+        output_dir.to_str().unwrap(),
+        config,
+    );
+    assert!(matches!(result, Err(AudioError::InvalidParameter(_))), "{:?}", result);
+    assert!(!output_dir.exists());
+}
+
+#[test]
+fn test_transcode_to_max_size() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output_path = "tests/test_data/capped.mp3";
+    let max_bytes = 50_000;
+    processor.transcode_to_max_size(AudioFormat::Mp3, max_bytes, output_path)
+        .expect("Size-capped transcode failed");
+    let size = fs::metadata(output_path).expect("Output missing").len();
+    assert!(size <= max_bytes, "output is {} bytes, over the {} byte cap", size, max_bytes);
+    assert!(matches!(
+        processor.transcode_to_max_size(AudioFormat::Mp3, 1_000, output_path),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_apply_stutter() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let stutter = AudioEffect::Stutter {
+        segment: Duration::from_millis(200),
+        repeats: 3,
+        at: Duration::from_secs(1),
+    };
+    let stuttered = processor.apply_effect(stutter).expect("Stutter failed");
+    let length = stuttered.duration().expect("Failed to probe duration").as_secs_f64();
+    assert!((length - 5.6).abs() < 0.1, "unexpected stuttered length {}", length);
+    let _ = fs::remove_file(&stuttered.file_path);
+
+    let too_late = AudioEffect::Stutter {
+        segment: Duration::from_secs(1),
+        repeats: 2,
+        at: Duration::from_secs(10),
+    };
+    assert!(matches!(processor.apply_effect(too_late), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_apply_bitcrush() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let crushed = processor.apply_effect(AudioEffect::Bitcrush { bits: 8, rate_divisor: 4 })
+        .expect("Bitcrush failed");
+    assert!(Path::new(&crushed.file_path).exists());
+    let _ = fs::remove_file(&crushed.file_path);
+
+    assert!(matches!(
+        processor.apply_effect(AudioEffect::Bitcrush { bits: 0, rate_divisor: 1 }),
+        Err(AudioError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_apply_compressor_metered() {
+    let file = setup_tone_file();
+    let loud_path = "tests/test_data/tone_loud.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-i", &file, "-af", "volume=16dB", loud_path, "-y"])
+        .status()
+        .expect("Failed to generate loud tone using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create loud tone");
+
+    let processor = AudioProcessor::new(loud_path).expect("Failed to create processor");
+    let params = CompressorParams { threshold_db: -20.0, ratio: 8.0, ..CompressorParams::default() };
+    let (compressed, reduction) = processor.apply_compressor_metered(params).expect("Compression failed");
+    assert!(Path::new(&compressed.file_path).exists());
+    assert!(reduction > 0.0, "expected positive gain reduction, got {}", reduction);
+    let _ = fs::remove_file(loud_path);
+    let _ = fs::remove_file(&compressed.file_path);
+}
+
+#[test]
+fn test_tee() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let mp3_path = "tests/test_data/tee.mp3".to_string();
+    let ogg_path = "tests/test_data/tee.ogg".to_string();
+    processor.tee(&[(AudioFormat::Mp3, mp3_path.clone()), (AudioFormat::Ogg, ogg_path.clone())])
+        .expect("Tee failed");
+    assert!(Path::new(&mp3_path).exists());
+    assert!(Path::new(&ogg_path).exists());
+
+    let copy_path = "tests/test_data/tee_copy.mp3".to_string();
+    processor.tee(&[(AudioFormat::Mp3, mp3_path.clone()), (AudioFormat::Mp3, copy_path.clone())])
+        .expect("Tee muxer failed");
+    assert!(Path::new(&copy_path).exists());
+
+    let _ = fs::remove_file(&mp3_path);
+    let _ = fs::remove_file(&ogg_path);
+    let _ = fs::remove_file(&copy_path);
+}
+
+#[test]
+fn test_trim_from_end() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let trimmed = processor.trim_from_end(Duration::from_secs(1), Duration::from_secs(1))
+        .expect("Trim from end failed");
+    let length = trimmed.duration().expect("Failed to probe duration").as_secs_f64();
+    assert!((length - 3.0).abs() < 0.1, "unexpected trimmed length {}", length);
+    let _ = fs::remove_file(&trimmed.file_path);
+
+    assert!(matches!(
+        processor.trim_from_end(Duration::from_secs(3), Duration::from_secs(3)),
+        Err(AudioError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_audio_only_drops_video() {
+    fs::create_dir_all("tests/test_data").expect("Failed to create test_data directory");
+    let av_path = "tests/test_data/av_input.mkv";
+    let status = std::process::Command::new("ffmpeg")
+        .args([
+            "-f", "lavfi", "-i", "testsrc=duration=2:size=160x120:rate=10",
+            "-f", "lavfi", "-i", "sine=frequency=440:duration=2",
+            "-shortest", av_path, "-y"
+        ])
+        .status()
+        .expect("Failed to generate A/V file using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create A/V file");
+
+    let processor = AudioProcessor::new(av_path).expect("Failed to create processor");
+    let output_path = "tests/test_data/av_output.mkv";
+    processor.transcode_with(output_path, TranscodeOptions::default()).expect("Transcode failed");
+    let probe = std::process::Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v", "-show_entries", "stream=index", "-of", "csv=p=0", output_path])
+        .output()
+        .expect("Failed to run ffprobe");
+    assert!(String::from_utf8_lossy(&probe.stdout).trim().is_empty(), "output still has a video stream");
+    let _ = fs::remove_file(av_path);
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_mix_with_headroom() {
+    fs::create_dir_all("tests/test_data").expect("Failed to create test_data directory");
+    let full_scale_path = "tests/test_data/full_scale.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-f", "lavfi", "-i", "aevalsrc=sin(2*PI*440*t):s=44100:d=3", full_scale_path, "-y"])
+        .status()
+        .expect("Failed to generate full-scale tone using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create full-scale tone");
+
+    let first = AudioProcessor::new(full_scale_path).expect("Failed to create processor");
+    let second = AudioProcessor::new(full_scale_path).expect("Failed to create processor");
+    let output_path = "tests/test_data/mixed.wav";
+    let mixed = AudioProcessor::mix(&[first, second], output_path).expect("Mix failed");
+    let peak_db = mixed.level_stats().expect("Level stats failed").peak_db;
+    assert!(peak_db < -2.0, "mix peaks at {} dBFS, expected at least 2 dB of headroom", peak_db);
+    let _ = fs::remove_file(full_scale_path);
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_transcode_with_options() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output_path = "tests/test_data/options.mp3";
+    let opts = TranscodeOptions::new()
+        .codec("libmp3lame")
+        .bitrate(BitrateMode::Constant(192))
+        .sample_rate(48000);
+    processor.transcode_with_options(opts, output_path).expect("Transcode with options failed");
+
+    let output = AudioProcessor::new(output_path).expect("Failed to open output");
+    assert_eq!(output.sample_rate().expect("Sample rate probe failed"), 48000);
+    let probe = std::process::Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "a:0", "-show_entries", "stream=codec_name,bit_rate", "-of", "csv=p=0", output_path])
+        .output()
+        .expect("Failed to run ffprobe");
+    let probed = String::from_utf8_lossy(&probe.stdout).trim().to_string();
+    assert_eq!(probed, "mp3,192000");
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_config_env() {
+    let file = setup_test_file();
+    let report_dir = tempfile::tempdir().expect("Failed to create report directory");
+    let report_path = report_dir.path().join("ffreport.log");
+    let mut config = AudioProcessorConfig::default();
+    config.env.insert("FFREPORT".to_string(), format!("file={}", report_path.display()));
+    let processor = AudioProcessor::with_config(&file, config).expect("Failed to create processor");
+    let output_path = "tests/test_data/env_report.mp3";
+    processor.transcode(AudioFormat::Mp3, output_path).expect("Transcode failed");
+    assert!(report_path.exists(), "FFREPORT log was not written");
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_db_linear_conversion() {
+    assert!((db_to_linear(0.0) - 1.0).abs() < 1e-6);
+    assert!(linear_to_db(1.0).abs() < 1e-6);
+    assert!((db_to_linear(6.0) - 2.0).abs() < 0.01);
+    assert!((linear_to_db(2.0) - 6.0).abs() < 0.03);
+    assert_eq!(linear_to_db(0.0), f32::NEG_INFINITY);
+    assert_eq!(linear_to_db(-1.0), f32::NEG_INFINITY);
+}
+
+#[test]
+fn test_overlay_many() {
+    let base = AudioProcessor::new(&setup_test_file()).expect("Failed to create base processor");
+    let tone = AudioProcessor::new(&setup_tone_file()).expect("Failed to create overlay processor");
+    let output_path = "tests/test_data/overlay_many.wav";
+    let overlayed = base
+        .overlay_many(&[(tone.clone(), Duration::from_secs(1)), (tone, Duration::from_secs(3))], output_path)
+        .expect("Overlay many failed");
+    assert!(Path::new(&overlayed.file_path).exists());
+    let length = overlayed.duration().expect("Failed to probe duration").as_secs_f64();
+    assert!((length - 5.0).abs() < 0.1, "unexpected overlay length {}", length);
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_read_cue_points() {
+    // Build a 1-second 8 kHz mono 16-bit WAV with cue points at 0.25s and 0.5s.
+    let sample_rate: u32 = 8000;
+    let data = vec![0u8; sample_rate as usize * 2];
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&1u16.to_le_bytes());
+    fmt.extend_from_slice(&1u16.to_le_bytes());
+    fmt.extend_from_slice(&sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    fmt.extend_from_slice(&2u16.to_le_bytes());
+    fmt.extend_from_slice(&16u16.to_le_bytes());
+    let mut cue = Vec::new();
+    cue.extend_from_slice(&2u32.to_le_bytes());
+    for (id, offset) in [(1u32, 2000u32), (2, 4000)] {
+        cue.extend_from_slice(&id.to_le_bytes());
+        cue.extend_from_slice(&offset.to_le_bytes());
+        cue.extend_from_slice(b"data");
+        cue.extend_from_slice(&0u32.to_le_bytes());
+        cue.extend_from_slice(&0u32.to_le_bytes());
+        cue.extend_from_slice(&offset.to_le_bytes());
+    }
+    let mut body = b"WAVE".to_vec();
+    for (id, chunk) in [(b"fmt ", &fmt), (b"data", &data), (b"cue ", &cue)] {
+        body.extend_from_slice(id);
+        body.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        body.extend_from_slice(chunk);
+    }
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&body);
+
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let wav_path = dir.path().join("cues.wav");
+    fs::write(&wav_path, &wav).expect("Failed to write WAV");
+    let processor = AudioProcessor::new(wav_path.to_str().unwrap()).expect("Failed to create processor");
+    let cues = processor.read_cue_points().expect("Reading cue points failed");
+    assert_eq!(cues, vec![Duration::from_millis(250), Duration::from_millis(500)]);
+}
+
+#[test]
+fn test_input_is_accessible() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let file_path = dir.path().join("present.wav");
+    fs::write(&file_path, b"not really audio").expect("Failed to write file");
+    assert!(input_is_accessible(file_path.to_str().unwrap()));
+    assert!(!input_is_accessible(dir.path().join("missing.wav").to_str().unwrap()));
+    #[cfg(unix)]
+    {
+        let link = dir.path().join("dangling.wav");
+        std::os::unix::fs::symlink(dir.path().join("gone.wav"), &link).expect("Failed to create symlink");
+        assert!(input_is_accessible(link.to_str().unwrap()));
+    }
+
+    assert!(input_is_accessible("anullsrc=r=44100:cl=stereo"));
+    assert!(input_is_accessible("sine=frequency=440"));
+
+    assert!(input_is_accessible("https://example.com/stream.mp3"));
+    assert!(input_is_accessible("rtmp://example.com/live"));
+    assert!(!input_is_accessible("C://not-a-scheme/missing.wav"));
+}
+
+#[test]
+fn test_apply_radio_voice() {
+    // A 1 kHz tone sits inside the radio band; a 10 kHz tone should be all but removed.
+    let mut peaks = Vec::new();
+    for (frequency, path) in [(1000, "tests/test_data/tone_1k.wav"), (10000, "tests/test_data/tone_10k.wav")] {
+        let status = std::process::Command::new("ffmpeg")
+            .args(["-f", "lavfi", "-i", &format!("sine=frequency={}:duration=2", frequency), path, "-y"])
+            .status()
+            .expect("Failed to generate tone using ffmpeg");
+        assert!(status.success(), "ffmpeg failed to create tone");
+
+        let processor = AudioProcessor::new(path).expect("Failed to create processor");
+        let radio = processor.apply_effect(AudioEffect::RadioVoice).expect("RadioVoice failed");
+        peaks.push(radio.level_stats().expect("Failed to read levels").peak_db);
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(&radio.file_path);
+    }
+    assert!(peaks[0] > -30.0, "in-band tone was attenuated to {} dB", peaks[0]);
+    assert!(peaks[1] < peaks[0] - 20.0, "out-of-band tone only fell to {} dB", peaks[1]);
+}
+
+#[test]
+fn test_transcode_progress_iter() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output = "tests/test_data/tone_progress.mp3";
+    let progress: Vec<f32> = processor
+        .transcode_progress_iter(AudioFormat::Mp3, output)
+        .collect::<Result<_, _>>()
+        .expect("Transcode failed");
+    assert_eq!(progress.last(), Some(&1.0));
+    assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]), "progress decreased: {:?}", progress);
+    assert!(Path::new(output).exists());
+    let _ = fs::remove_file(output);
+}
+
+#[test]
+fn test_to_asr_wav() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output = "tests/test_data/tone_asr.wav";
+    let asr = processor.to_asr_wav(output).expect("ASR conversion failed");
+    assert_eq!(asr.sample_rate().expect("Sample rate probe failed"), 16000);
+    assert_eq!(asr.channels().expect("Channel probe failed"), 1);
+    let probe = std::process::Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "a:0", "-show_entries", "stream=sample_fmt", "-of", "default=nw=1:nk=1", output])
+        .output()
+        .expect("Failed to run ffprobe");
+    assert_eq!(String::from_utf8_lossy(&probe.stdout).trim(), "s16");
+    let _ = fs::remove_file(output);
+}
+
+#[test]
+fn test_duration_without_format_duration() {
+    // Matroska written to a pipe can't seek back to fill in the header's duration.
+    let file = setup_tone_file();
+    let output = "tests/test_data/tone_streamed.mkv";
+    let mkv = std::process::Command::new("ffmpeg")
+        .args(["-v", "error", "-i", &file, "-c:a", "flac", "-f", "matroska", "-"])
+        .output()
+        .expect("Failed to stream matroska using ffmpeg");
+    assert!(mkv.status.success(), "ffmpeg failed to stream matroska");
+    fs::write(output, &mkv.stdout).expect("Failed to write matroska file");
+
+    let processor = AudioProcessor::new(output).expect("Failed to create processor");
+    let duration = processor.duration().expect("Duration probe failed");
+    assert!((duration.as_secs_f64() - 5.0).abs() < 0.5, "unexpected duration {:?}", duration);
+    let _ = fs::remove_file(output);
+}
+
+#[test]
+fn test_normalize_mode() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+
+    let peak = processor.normalize_mode(NormalizeMode::Peak { target_db: -3.0 }).expect("Peak normalize failed");
+    let peak_db = peak.level_stats().expect("Failed to read levels").peak_db;
+    assert!((peak_db + 3.0).abs() < 0.5, "peak normalized to {} dB", peak_db);
+    let _ = fs::remove_file(&peak.file_path);
+
+    let rms = processor.normalize_mode(NormalizeMode::Rms { target_db: -20.0 }).expect("RMS normalize failed");
+    let rms_db = rms.level_stats().expect("Failed to read levels").rms_db;
+    assert!((rms_db + 20.0).abs() < 0.5, "RMS normalized to {} dB", rms_db);
+    let _ = fs::remove_file(&rms.file_path);
+
+    let loud = processor.normalize_mode(NormalizeMode::Loudness { target_lufs: -23.0 }).expect("Loudness normalize failed");
+    let lufs = loud.integrated_loudness().expect("Failed to measure loudness");
+    assert!((lufs + 23.0).abs() < 1.5, "loudness normalized to {} LUFS", lufs);
+    let _ = fs::remove_file(&loud.file_path);
+
+    assert!(matches!(
+        processor.normalize_mode(NormalizeMode::Peak { target_db: 3.0 }),
+        Err(AudioError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_change_speed_rejects_degenerate_factors() {
+    // The factor is rejected before ffmpeg runs, so any existing file will do.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let path = fake_input(dir.path(), "input.wav");
+    let processor = AudioProcessor::new(path.to_str().unwrap()).expect("Failed to create processor");
+    for factor in [0.0, -1.0, f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        assert!(
+            matches!(processor.change_speed(factor), Err(AudioError::InvalidParameter(_))),
+            "factor {} was not rejected", factor
+        );
+        assert!(matches!(processor.config.speed_factor(factor), Err(AudioError::InvalidParameter(_))));
+    }
+}
+
+#[test]
+fn test_process_channels_independently() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output = "tests/test_data/tone_left_boosted.wav";
+    let index = std::cell::Cell::new(0);
+    let joined = processor
+        .process_channels_independently(
+            |channel| {
+                let current = index.get();
+                index.set(current + 1);
+                if current == 0 { channel.adjust_volume(4.0) } else { Ok(channel) }
+            },
+            output,
+        )
+        .expect("Per-channel processing failed");
+    assert_eq!(joined.channels().expect("Channel probe failed"), 2);
+
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let channels = joined.split_channels(dir.path().to_str().unwrap()).expect("Channel split failed");
+    let left = channels[0].level_stats().expect("Failed to read levels").peak_db;
+    let right = channels[1].level_stats().expect("Failed to read levels").peak_db;
+    assert!(left > right + 6.0, "left peak {} dB not boosted over right {} dB", left, right);
+    let _ = fs::remove_file(output);
+}
+
+#[test]
+fn test_dynamic_range() {
+    // Decaying bursts have a high crest factor; heavy compression flattens it.
+    let path = "tests/test_data/bursts.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-f", "lavfi", "-i", "aevalsrc=0.9*sin(2*PI*440*t)*exp(-8*mod(t\\,0.5)):s=44100:d=12", path, "-y"])
+        .status()
+        .expect("Failed to generate bursts using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create bursts");
+
+    let processor = AudioProcessor::new(path).expect("Failed to create processor");
+    let params = CompressorParams {
+        threshold_db: -40.0,
+        ratio: 20.0,
+        attack: Duration::from_millis(1),
+        makeup_db: 20.0,
+        ..CompressorParams::default()
+    };
+    let compressed = processor.apply_effect(AudioEffect::Compressor(params)).expect("Compression failed");
+    let original_dr = processor.dynamic_range().expect("DR measurement failed");
+    let compressed_dr = compressed.dynamic_range().expect("DR measurement failed");
+    assert!(compressed_dr < original_dr, "compressed DR {} not below original {}", compressed_dr, original_dr);
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(&compressed.file_path);
+}
+
+#[test]
+fn test_adjust_volume_clipping_policy() {
+    // The tone peaks around -18 dBFS, so a 16x (+24 dB) boost would clip.
+    let file = setup_tone_file();
+    let with_policy = |clipping_policy| {
+        let config = AudioProcessorConfig { clipping_policy, ..AudioProcessorConfig::default() };
+        AudioProcessor::with_config(&file, config).expect("Failed to create processor")
+    };
+
+    let clipped = with_policy(ClippingPolicy::Allow).adjust_volume(16.0).expect("Allowed boost failed");
+    let peak = clipped.level_stats().expect("Failed to read levels").peak_db;
+    assert!(peak > -0.1, "expected the allowed boost to clip, peak {} dB", peak);
+    let _ = fs::remove_file(&clipped.file_path);
+
+    let limited = with_policy(ClippingPolicy::Limit).adjust_volume(16.0).expect("Limited boost failed");
+    let peak = limited.level_stats().expect("Failed to read levels").peak_db;
+    assert!(peak < 0.0, "expected the limited boost to stay under 0 dB, peak {} dB", peak);
+    let _ = fs::remove_file(&limited.file_path);
+
+    assert!(matches!(
+        with_policy(ClippingPolicy::Reject).adjust_volume(16.0),
+        Err(AudioError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_validate_filtergraph() {
+    assert!(validate_filtergraph("highpass=f=80,volume=0.5").is_ok());
+    match validate_filtergraph("volume=0.5,nosuchfilter=3") {
+        Err(AudioError::FfmpegFailed { stderr, .. }) => {
+            assert!(stderr.contains("nosuchfilter"), "error doesn't name the bad filter: {}", stderr);
+        }
+        other => panic!("expected an FfmpegFailed error, got {:?}", other),
+    }
+
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    assert!(matches!(processor.apply_raw_filter("volume=="), Err(AudioError::FfmpegFailed { .. })));
+    let filtered = processor.apply_filter_complex("[0:a]volume=0.5").expect("Filter complex failed");
+    assert!(Path::new(&filtered.file_path).exists());
+    let _ = fs::remove_file(&filtered.file_path);
+}
+
+#[test]
+fn test_conform_to() {
+    let reference_file = setup_tone_file();
+    let path = "tests/test_data/tone_16k_mono.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-f", "lavfi", "-i", "sine=frequency=440:sample_rate=16000:duration=2", "-ac", "1", path, "-y"])
+        .status()
+        .expect("Failed to generate tone using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create 16 kHz tone");
+
+    let reference = AudioProcessor::new(&reference_file).expect("Failed to create processor");
+    let processor = AudioProcessor::new(path).expect("Failed to create processor");
+    let conformed = processor.conform_to(&reference).expect("Conform failed");
+    assert_eq!(conformed.sample_rate().expect("Sample rate probe failed"), 44100);
+    assert_eq!(conformed.channels().expect("Channel probe failed"), 2);
+    assert_eq!(
+        conformed.sample_format().expect("Sample format probe failed"),
+        reference.sample_format().expect("Sample format probe failed")
+    );
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(&conformed.file_path);
+}
+
+#[test]
+fn test_trim_silence_edges_keeps_padding() {
+    // One second of silence either side of a two-second tone.
+    let path = "tests/test_data/padded_edges_tone.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-f", "lavfi", "-i", "aevalsrc=if(between(t\\,1\\,3)\\,0.5*sin(2*PI*440*t)\\,0):s=44100:d=4", path, "-y"])
+        .status()
+        .expect("Failed to generate padded tone using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create padded tone");
+
+    let processor = AudioProcessor::new(path).expect("Failed to create processor");
+    let trimmed = processor.trim_silence_edges(-50.0, Duration::from_millis(200)).expect("Silence trim failed");
+    let duration = trimmed.duration().expect("Failed to probe duration").as_secs_f64();
+    assert!((duration - 2.4).abs() < 0.15, "unexpected trimmed duration {}", duration);
+
+    let head = trimmed.apply_raw_filter("atrim=end=0.15").expect("Failed to cut head");
+    let head_peak = head.level_stats().expect("Failed to read levels").peak_db;
+    assert!(head_peak < -60.0, "expected silence kept at the start, peak {} dB", head_peak);
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(&trimmed.file_path);
+    let _ = fs::remove_file(&head.file_path);
+}
+
+#[test]
+fn test_write_wav() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let path = dir.path().join("tone.wav");
+    let path = path.to_str().unwrap();
+    let sample_rate = 8000;
+    let samples: Vec<f32> = (0..sample_rate)
+        .flat_map(|n| {
+            let value = (2.0 * std::f32::consts::PI * 440.0 * n as f32 / sample_rate as f32).sin() * 0.5;
+            [value, -value]
+        })
+        .collect();
+    write_wav(path, &samples, sample_rate, 2).expect("WAV write failed");
+
+    let header = read_wav_header(path).expect("WAV header read failed");
+    assert_eq!(header.audio_format, 1);
+    assert_eq!(header.channels, 2);
+    assert_eq!(header.sample_rate, sample_rate);
+    assert_eq!(header.bits_per_sample, 16);
+    assert_eq!(header.data_len as usize, samples.len() * 2);
+    assert_eq!(fs::metadata(path).unwrap().len(), 44 + header.data_len as u64);
+
+    assert!(matches!(write_wav(path, &samples[..3], sample_rate, 2), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_find_offset() {
+    // Noise rather than a tone, whose periodicity would make the correlation ambiguous.
+    let path = "tests/test_data/noise.wav";
+    let delayed_path = "tests/test_data/noise_delayed.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-f", "lavfi", "-i", "anoisesrc=d=3:seed=42:a=0.5", path, "-y"])
+        .status()
+        .expect("Failed to generate noise using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create noise");
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-i", path, "-af", "adelay=delays=500:all=1", delayed_path, "-y"])
+        .status()
+        .expect("Failed to delay noise using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to delay noise");
+
+    let original = AudioProcessor::new(path).expect("Failed to create processor");
+    let delayed = AudioProcessor::new(delayed_path).expect("Failed to create processor");
+    let offset = original.find_offset(&delayed).expect("Offset detection failed");
+    assert!((offset.as_secs_f64() - 0.5).abs() < 0.005, "detected offset {:?}", offset);
+    assert!(matches!(delayed.find_offset(&original), Err(AudioError::InvalidParameter(_))));
+
+    let aligned = original.align_to(&delayed).expect("Alignment failed");
+    let realigned = original.find_offset(&aligned).expect("Offset detection failed");
+    assert!(realigned.as_secs_f64() < 0.005, "aligned copy still offset by {:?}", realigned);
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(delayed_path);
+    let _ = fs::remove_file(&aligned.file_path);
+}
+
+#[test]
+fn test_atempo_chain() {
+    assert_eq!(atempo_chain(4.0).unwrap(), "atempo=2,atempo=2");
+    assert_eq!(atempo_chain(0.25).unwrap(), "atempo=0.5,atempo=0.5");
+    assert_eq!(atempo_chain(1.5).unwrap(), "atempo=1.5");
+    assert_eq!(atempo_chain(3.0).unwrap(), "atempo=2,atempo=1.5");
+    assert_eq!(atempo_chain(0.3).unwrap(), "atempo=0.5,atempo=0.6");
+    assert_eq!(atempo_chain(1.0).unwrap(), "atempo=1");
+    assert_eq!(atempo_chain(8.0).unwrap(), "atempo=2,atempo=2,atempo=2");
+    for factor in [0.0, -2.0, f32::NAN] {
+        assert!(matches!(atempo_chain(factor), Err(AudioError::InvalidParameter(_))));
+    }
+}
+
+#[test]
+fn test_transcode_for_quality() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    for (quality, extension) in [
+        (QualityTarget::Lossless, "flac"),
+        (QualityTarget::HighLossy, "mp3"),
+        (QualityTarget::SmallSize, "opus"),
+    ] {
+        let output = processor
+            .transcode_for_quality(quality, "tests/test_data/tone_quality")
+            .expect("Quality transcode failed");
+        assert_eq!(Path::new(&output).extension().and_then(|ext| ext.to_str()), Some(extension));
+        assert!(Path::new(&output).exists());
+        let _ = fs::remove_file(&output);
+    }
+}
+
+#[test]
+fn test_wav_bit_depth() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output = "tests/test_data/tone_24bit.wav";
+    let opts = TranscodeOptions::new().wav_bit_depth(WavBitDepth::Int24);
+    processor.transcode_with(output, opts).expect("24-bit transcode failed");
+    let probe = std::process::Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "a:0", "-show_entries", "stream=codec_name", "-of", "default=nw=1:nk=1", output])
+        .output()
+        .expect("Failed to run ffprobe");
+    assert_eq!(String::from_utf8_lossy(&probe.stdout).trim(), "pcm_s24le");
+    assert_eq!(read_wav_header(output).expect("WAV header read failed").bits_per_sample, 24);
+    let _ = fs::remove_file(output);
+
+    assert_eq!(WavBitDepth::from_bits(32, true).unwrap(), WavBitDepth::Float32);
+    assert!(matches!(WavBitDepth::from_bits(20, false), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_silence_report() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let report = processor.silence_report(-50.0, Duration::from_millis(500)).expect("Silence report failed");
+    assert!((report.silence_ratio - 1.0).abs() < 0.02, "silence ratio {}", report.silence_ratio);
+    assert_eq!(report.regions.len(), 1);
+    assert!((report.total_silence.as_secs_f64() - 5.0).abs() < 0.1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failed_run_removes_partial_output() {
+    // A stand-in ffmpeg that writes part of its output and then fails.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let script = "[ \"$1\" = -version ] && exit 0\nfor last; do :; done\necho partial > \"$last\"\nexit 1\n";
+    fake_tool(dir.path(), "ffmpeg", script);
+    let input = fake_input(dir.path(), "input.wav");
+    let output = dir.path().join("volume_adjusted_input.wav");
+
+    for keep_partial_output in [false, true] {
+        let mut config = AudioProcessorConfig { keep_partial_output, ..AudioProcessorConfig::default() };
+        config.env.insert("PATH".to_string(), dir.path().to_string_lossy().into_owned());
+        let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+        assert!(matches!(processor.adjust_volume(0.5), Err(AudioError::FfmpegFailed { .. })));
+        assert_eq!(output.exists(), keep_partial_output);
+    }
+}
+
+#[test]
+fn test_apply_overdrive() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let driven = processor.apply_effect(AudioEffect::Overdrive { gain: 8.0, tone: 0.5 }).expect("Overdrive failed");
+    assert!(Path::new(&driven.file_path).exists());
+    let peak = driven.level_stats().expect("Failed to read levels").peak_db;
+    assert!(peak <= 0.0 && peak > -12.0, "unexpected overdrive peak {} dB", peak);
+    let _ = fs::remove_file(&driven.file_path);
+
+    for effect in [AudioEffect::Overdrive { gain: 1.0, tone: 0.5 }, AudioEffect::Overdrive { gain: 4.0, tone: 1.5 }] {
+        assert!(matches!(processor.apply_effect(effect), Err(AudioError::InvalidParameter(_))));
+    }
+}
+
+#[test]
+fn test_fade_in_free_function() {
+    let file = setup_tone_file();
+    let output = "tests/test_data/tone_faded_in.wav";
+    fade_in(&file, output, Duration::from_secs(2)).expect("Fade in failed");
+    let faded = AudioProcessor::new(output).expect("Failed to create processor");
+    let head = faded.apply_raw_filter("atrim=end=0.1").expect("Failed to cut head");
+    let head_peak = head.level_stats().expect("Failed to read levels").peak_db;
+    assert!(head_peak < -30.0, "expected a quiet start, peak {} dB", head_peak);
+    let _ = fs::remove_file(output);
+    let _ = fs::remove_file(&head.file_path);
+}
+
+#[test]
+fn test_set_volume_free_function() {
+    let file = setup_tone_file();
+    let output = "tests/test_data/tone_half_volume.wav";
+    set_volume(&file, output, 0.5).expect("Set volume failed");
+    let original = AudioProcessor::new(&file).expect("Failed to create processor");
+    let quieter = AudioProcessor::new(output).expect("Failed to create processor");
+    let drop = original.level_stats().unwrap().peak_db - quieter.level_stats().unwrap().peak_db;
+    assert!((drop - 6.0).abs() < 0.5, "expected a 6 dB drop, got {} dB", drop);
+    let _ = fs::remove_file(output);
+}
+
+#[test]
+fn test_merge_conforms_channel_counts() {
+    let stereo_file = setup_tone_file();
+    let mono_path = "tests/test_data/tone_mono.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-i", &stereo_file, "-ac", "1", mono_path, "-y"])
+        .status()
+        .expect("Failed to downmix tone using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create mono tone");
+
+    let config = AudioProcessorConfig { merge_mismatch_policy: MergeMismatchPolicy::ConformToMax, ..AudioProcessorConfig::default() };
+    let mono = AudioProcessor::with_config(mono_path, config).expect("Failed to create processor");
+    let stereo = AudioProcessor::new(&stereo_file).expect("Failed to create processor");
+    let output = "tests/test_data/merged_mixed_channels.wav";
+    let merged = AudioProcessor::merge_audios(&[mono.clone(), stereo.clone()], output).expect("Merge failed");
+    assert_eq!(merged.channels().expect("Channel probe failed"), 2);
+    let duration = merged.duration().expect("Failed to probe duration").as_secs_f64();
+    assert!((duration - 10.0).abs() < 0.1, "unexpected merged duration {}", duration);
+    let _ = fs::remove_file(output);
+
+    // The merge follows the first input's config, which defaults to rejecting mismatches.
+    assert!(matches!(
+        AudioProcessor::merge_audios(&[stereo, mono], output),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let _ = fs::remove_file(mono_path);
+}
+
+#[test]
+fn test_watch_directory() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let processed = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorder = std::sync::Arc::clone(&processed);
+    let handle = AudioProcessor::watch_directory(dir.path().to_str().unwrap(), "wav", move |processor| {
+        recorder.lock().unwrap().push(processor.file_path.clone());
+        Ok(processor)
+    })
+    .expect("Failed to watch directory");
+
+    fs::write(dir.path().join("ignored.txt"), b"not audio").expect("Failed to write file");
+    let dropped = dir.path().join("dropped.wav");
+    fs::write(&dropped, b"placeholder").expect("Failed to write file");
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while processed.lock().unwrap().is_empty() && std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    handle.stop();
+    assert_eq!(*processed.lock().unwrap(), vec![dropped.to_string_lossy().into_owned()]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ffmpeg_failure_captures_stderr() {
+    // A stand-in ffmpeg that floods stderr before failing.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let script = "[ \"$1\" = -version ] && exit 0\nhead -c 20000 /dev/zero | tr '\\0' x >&2\necho 'Invalid argument' >&2\nexit 3\n";
+    fake_tool(dir.path(), "ffmpeg", script);
+    let input = fake_input(dir.path(), "input.wav");
+
+    let mut config = AudioProcessorConfig::default();
+    config.env.insert("PATH".to_string(), format!("{}:/usr/bin:/bin", dir.path().display()));
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+    match processor.reverse() {
+        Err(AudioError::FfmpegFailed { operation, stderr, exit_code }) => {
+            assert_eq!(operation, "reverse");
+            assert_eq!(exit_code, Some(3));
+            assert!(stderr.ends_with("Invalid argument"), "stderr tail lost: {}", &stderr[stderr.len() - 40..]);
+            assert!(stderr.len() <= 8 * 1024 + 3, "stderr not capped: {} bytes", stderr.len());
+        }
+        other => panic!("expected an FfmpegFailed error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_working_dir() {
+    let file = setup_tone_file();
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    fs::copy(&file, dir.path().join("relative.wav")).expect("Failed to copy fixture");
+
+    let config = AudioProcessorConfig { working_dir: Some(dir.path().to_path_buf()), ..AudioProcessorConfig::default() };
+    let processor = AudioProcessor::with_config("relative.wav", config).expect("Failed to create processor");
+    let reversed = processor.reverse().expect("Reverse failed");
+    assert_eq!(reversed.file_path, "reversed_relative.wav");
+    assert!(dir.path().join("reversed_relative.wav").exists());
+    assert!(!Path::new("reversed_relative.wav").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_working_dir_keeps_url_and_lavfi_inputs() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(dir.path(), "ffmpeg", "exit 0\n");
+    let config = AudioProcessorConfig {
+        working_dir: Some(dir.path().to_path_buf()),
+        ffmpeg_path: Some(fake_ffmpeg),
+        ..AudioProcessorConfig::default()
+    };
+    AudioProcessor::with_config("sine=frequency=440", config.clone()).expect("lavfi input rejected");
+    AudioProcessor::with_config("http://example.com/a.mp3", config.clone()).expect("URL input rejected");
+    assert!(matches!(
+        AudioProcessor::with_config("missing.wav", config),
+        Err(AudioError::IoError(_))
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_overlay_many_accepts_lavfi_overlay() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\necho \"$*\" > args.log\nfor last; do :; done\necho rendered > \"$last\"\n",
+    );
+    let config = AudioProcessorConfig {
+        working_dir: Some(dir.path().to_path_buf()),
+        ffmpeg_path: Some(fake_ffmpeg),
+        ..AudioProcessorConfig::default()
+    };
+    fake_input(dir.path(), "bed.wav");
+    let bed = AudioProcessor::with_config("bed.wav", config.clone()).expect("Failed to create processor");
+    let beep = AudioProcessor::with_config("sine=frequency=880:duration=1", config).expect("Failed to create processor");
+    bed.overlay_many(&[(beep, Duration::from_secs(1))], "mixed.wav").expect("Overlay many failed");
+    assert!(dir.path().join("mixed.wav").exists());
+    let args = fs::read_to_string(dir.path().join("args.log")).expect("Failed to read ffmpeg arguments");
+    assert!(args.contains("-f lavfi -i sine=frequency=880:duration=1"), "{}", args);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_with_ffmpeg_path() {
+    // A stand-in ffmpeg outside PATH that just writes its output file.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "custom-ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\nfor last; do :; done\necho rendered > \"$last\"\n",
+    );
+    let input = fake_input(dir.path(), "input.wav");
+
+    let processor = AudioProcessor::with_ffmpeg_path(input.to_str().unwrap(), fake_ffmpeg.to_str().unwrap())
+        .expect("Failed to create processor");
+    let reversed = processor.reverse().expect("Reverse with custom ffmpeg failed");
+    assert_eq!(fs::read_to_string(&reversed.file_path).unwrap(), "rendered\n");
+
+    let merged_path = dir.path().join("merged.wav");
+    AudioProcessor::merge_audios(&[reversed], merged_path.to_str().unwrap()).expect("Merge with custom ffmpeg failed");
+    assert!(merged_path.exists());
+}
+
+#[test]
+fn test_generate_click_track() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let path = dir.path().join("click.wav");
+    let path = path.to_str().unwrap();
+    let track = generate_click_track(120.0, 4, 44100, path).expect("Click track generation failed");
+    assert_eq!(track.file_path, path);
+    let header = read_wav_header(path).expect("WAV header read failed");
+    let secs = f64::from(header.data_len) / f64::from(header.sample_rate * u32::from(header.channels) * 2);
+    assert!((secs - 2.0).abs() < 0.01, "unexpected click track length {}", secs);
+
+    assert!(matches!(generate_click_track(0.0, 4, 44100, path), Err(AudioError::InvalidParameter(_))));
+    assert!(matches!(generate_click_track(120.0, 0, 44100, path), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_ffmpeg_not_found() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let input = fake_input(dir.path(), "input.wav");
+    let missing = dir.path().join("no-such-ffmpeg");
+    match AudioProcessor::with_ffmpeg_path(input.to_str().unwrap(), missing.to_str().unwrap()) {
+        Err(AudioError::FfmpegNotFound(message)) => assert!(message.contains("no-such-ffmpeg"), "{}", message),
+        other => panic!("expected FfmpegNotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_write_loudness_tags() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let ogg_path = "tests/test_data/tone_untagged.ogg";
+    processor.transcode(AudioFormat::Ogg, ogg_path).expect("Transcode failed");
+    let ogg = AudioProcessor::new(ogg_path).expect("Failed to create processor");
+    let tagged = ogg.write_loudness_tags("tests/test_data/tone_tagged.ogg").expect("Tagging failed");
+
+    let probe = std::process::Command::new("ffprobe")
+        .args([
+            "-v", "error", "-select_streams", "a:0",
+            "-show_entries", "stream_tags=REPLAYGAIN_TRACK_GAIN,REPLAYGAIN_TRACK_PEAK",
+            "-of", "default=nw=1", &tagged.file_path,
+        ])
+        .output()
+        .expect("Failed to run ffprobe");
+    let tags = String::from_utf8_lossy(&probe.stdout).to_uppercase();
+    assert!(tags.contains("REPLAYGAIN_TRACK_GAIN="), "missing gain tag in {}", tags);
+    assert!(tags.contains("REPLAYGAIN_TRACK_PEAK="), "missing peak tag in {}", tags);
+    // The tone is around -21 LUFS, so the gain to the -18 LUFS reference is positive.
+    assert!(tags.contains("REPLAYGAIN_TRACK_GAIN=+"), "unexpected gain in {}", tags);
+    let _ = fs::remove_file(ogg_path);
+    let _ = fs::remove_file(&tagged.file_path);
+}
+
+#[test]
+fn test_reverse_preserves_tags() {
+    fs::create_dir_all("tests/test_data").expect("Failed to create test_data directory");
+    let tagged_path = "tests/test_data/tagged.mp3";
+    let status = std::process::Command::new("ffmpeg")
+        .args([
+            "-f", "lavfi", "-i", "sine=frequency=440:duration=2",
+            "-metadata", "title=Test Tone", "-metadata", "artist=Audio Processor",
+            tagged_path, "-y"
+        ])
+        .status()
+        .expect("Failed to generate tagged MP3 using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create tagged MP3");
+
+    let processor = AudioProcessor::new(tagged_path).expect("Failed to create processor");
+    let reversed = processor.reverse().expect("Reverse failed");
+    let probe = std::process::Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format_tags=title,artist", "-of", "default=nw=1", &reversed.file_path])
+        .output()
+        .expect("Failed to run ffprobe");
+    let tags = String::from_utf8_lossy(&probe.stdout).to_lowercase();
+    assert!(tags.contains("title=test tone"), "title lost: {}", tags);
+    assert!(tags.contains("artist=audio processor"), "artist lost: {}", tags);
+    let _ = fs::remove_file(tagged_path);
+    let _ = fs::remove_file(&reversed.file_path);
+}
+
+#[test]
+fn test_transcode_honours_preserve_metadata() {
+    fs::create_dir_all("tests/test_data").expect("Failed to create test_data directory");
+    let tagged_path = "tests/test_data/tagged_strip.mp3";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=2", "-metadata", "title=Test Tone", tagged_path, "-y"])
+        .status()
+        .expect("Failed to generate tagged MP3 using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create tagged MP3");
+
+    let config = AudioProcessorConfig { preserve_metadata: false, ..AudioProcessorConfig::default() };
+    let processor = AudioProcessor::with_config(tagged_path, config).expect("Failed to create processor");
+    let output_path = "tests/test_data/stripped.mp3";
+    processor.transcode(AudioFormat::Mp3, output_path).expect("Transcode failed");
+    let probe = std::process::Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format_tags=title", "-of", "default=nw=1", output_path])
+        .output()
+        .expect("Failed to run ffprobe");
+    let tags = String::from_utf8_lossy(&probe.stdout).to_lowercase();
+    assert!(!tags.contains("test tone"), "title kept: {}", tags);
+    let _ = fs::remove_file(tagged_path);
+    let _ = fs::remove_file(output_path);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_transcode_metadata_mapping_args() {
+    // A stand-in ffmpeg that records its arguments.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let log = dir.path().join("ffmpeg.log");
+    let script = format!(
+        "[ \"$1\" = -version ] && exit 0\n\
+         [ \"$2\" = -encoders ] && printf ' ------\\n A....D libmp3lame  MP3\\n' && exit 0\n\
+         echo \"$*\" > '{}'\n",
+        log.display()
+    );
+    let fake_ffmpeg = fake_tool(dir.path(), "ffmpeg", &script);
+    let input = fake_input(dir.path(), "input.wav");
+    let output = dir.path().join("output.mp3");
+    let config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg),
+        preserve_metadata: false,
+        ..AudioProcessorConfig::default()
+    };
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+
+    processor.transcode(AudioFormat::Mp3, output.to_str().unwrap()).expect("Transcode failed");
+    let args = fs::read_to_string(&log).expect("Failed to read ffmpeg log");
+    assert!(args.contains("-map_metadata -1") && !args.contains("-map_metadata 0"), "{}", args);
+
+    let options = TranscodeOptions::new().preserve_metadata(true);
+    processor.transcode_with(output.to_str().unwrap(), options).expect("Transcode failed");
+    let args = fs::read_to_string(&log).expect("Failed to read ffmpeg log");
+    assert!(args.contains("-map_metadata 0") && !args.contains("-map_metadata -1"), "{}", args);
+}
+
+#[test]
+fn test_explicit_output_paths() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let out_dir = tempfile::tempdir().expect("Failed to create output directory");
+    let trimmed_path = out_dir.path().join("clip.wav").to_string_lossy().into_owned();
+    let trimmed = processor
+        .trim_to(Duration::from_secs(1), Duration::from_secs(3), &trimmed_path)
+        .expect("trim_to failed");
+    assert_eq!(trimmed.file_path, trimmed_path);
+    assert!(std::path::Path::new(&trimmed_path).exists());
+
+    // The default names keep the file in the input's directory rather than stacking
+    // a prefix onto the whole path.
+    let reversed = trimmed.reverse().expect("Reverse failed");
+    assert_eq!(
+        std::path::Path::new(&reversed.file_path),
+        out_dir.path().join("reversed_clip.wav")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_transcode_spawn_cancel() {
+    // A stand-in ffmpeg that writes part of its output and then hangs.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "slow-ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\n\
+         [ \"$2\" = -encoders ] && printf ' ------\\n A....D libmp3lame  MP3\\n' && exit 0\n\
+         for last; do :; done\necho partial > \"$last\"\nexec sleep 30\n",
+    );
+    let input = fake_input(dir.path(), "input.wav");
+    let output = dir.path().join("output.mp3");
+
+    let processor = AudioProcessor::with_ffmpeg_path(input.to_str().unwrap(), fake_ffmpeg.to_str().unwrap())
+        .expect("Failed to create processor");
+    let started = std::time::Instant::now();
+    let handle = processor.transcode_spawn(AudioFormat::Mp3, output.to_str().unwrap()).expect("Spawn failed");
+    handle.cancel().expect("Cancel failed");
+    assert!(matches!(handle.wait(), Err(AudioError::Cancelled(_))));
+    assert!(started.elapsed() < Duration::from_secs(10), "cancel did not stop ffmpeg");
+    assert!(!output.exists(), "cancelled transcode left its output behind");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_duration_probe_errors() {
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(dir.path(), "ffmpeg", "exit 0\n");
+    // An ffprobe that succeeds but reports nothing usable.
+    let fake_ffprobe = fake_tool(dir.path(), "garbled-ffprobe", "echo not-a-number\n");
+    let input = fake_input(dir.path(), "input.wav");
+
+    let config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg.clone()),
+        ffprobe_path: Some(fake_ffprobe),
+        ..AudioProcessorConfig::default()
+    };
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+    assert!(matches!(processor.duration(), Err(AudioError::InvalidParameter(_))));
+
+    let config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg),
+        ffprobe_path: Some(dir.path().join("missing-ffprobe")),
+        ..AudioProcessorConfig::default()
+    };
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+    assert!(matches!(processor.duration(), Err(AudioError::FfmpegNotFound(_))));
+}
+
+#[test]
+fn test_trim_zero_crossing() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output_path = "tests/test_data/zero_crossing_trim.wav";
+    // Neither cut lands on a zero crossing of the 440 Hz tone as requested.
+    let trimmed = processor
+        .trim_zero_crossing_to(Duration::from_micros(1_000_600), Duration::from_micros(2_000_600), output_path)
+        .expect("Zero crossing trim failed");
+
+    let pcm = std::process::Command::new("ffmpeg")
+        .args(["-v", "error", "-i", &trimmed.file_path, "-ac", "1", "-f", "f32le", "pipe:1"])
+        .output()
+        .expect("Failed to decode trimmed file")
+        .stdout;
+    let samples: Vec<f32> = pcm.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    let (first, last) = (samples[0], samples[samples.len() - 1]);
+    // The tone moves at most ~0.008 per sample, so anything this small is at a crossing.
+    assert!(first.abs() < 0.01, "first sample {} is not near zero", first);
+    assert!(last.abs() < 0.01, "last sample {} is not near zero", last);
+    assert!((samples.len() as f64 / 44100.0 - 1.0).abs() < 0.002, "unexpected length {}", samples.len());
+    assert!(matches!(
+        processor.trim_zero_crossing_to(Duration::from_secs(6), Duration::from_secs(7), output_path),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_fade_out_at_end() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let faded = processor.apply_effect(AudioEffect::FadeOut(Duration::from_secs(2))).expect("Fade out failed");
+
+    let pcm = std::process::Command::new("ffmpeg")
+        .args(["-v", "error", "-i", &faded.file_path, "-ac", "1", "-ar", "44100", "-f", "f32le", "pipe:1"])
+        .output()
+        .expect("Failed to decode faded file")
+        .stdout;
+    let samples: Vec<f32> = pcm.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    let rms = |from: f64, to: f64| {
+        let window = &samples[(from * 44100.0) as usize..(to * 44100.0) as usize];
+        (window.iter().map(|s| s * s).sum::<f32>() / window.len() as f32).sqrt()
+    };
+    let middle = rms(2.0, 2.5);
+    let end = rms(4.5, 4.9);
+    assert!(middle > 0.05, "the fade started too early (middle RMS {})", middle);
+    assert!(end < middle * 0.5, "end RMS {} is not below middle RMS {}", end, middle);
+    let _ = fs::remove_file(&faded.file_path);
+}
+
+#[test]
+fn test_estimate_output_space() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    // 5 s of 44.1 kHz stereo 16-bit PCM is 882,000 bytes.
+    let wav = processor.estimate_output_space(&Operation::Transcode(AudioFormat::Wav)).expect("Estimate failed");
+    assert!((850_000..1_000_000).contains(&wav), "implausible WAV estimate {}", wav);
+    let mp3 = processor.estimate_output_space(&Operation::Transcode(AudioFormat::Mp3)).expect("Estimate failed");
+    assert!(mp3 > 0 && mp3 < wav / 4, "implausible MP3 estimate {}", mp3);
+    let trimmed = processor
+        .estimate_output_space(&Operation::Trim { start: Duration::from_secs(1), end: Duration::from_secs(2) })
+        .expect("Estimate failed");
+    assert!(trimmed < wav / 3, "trim estimate {} should be about a fifth of the file", trimmed);
+    processor
+        .ensure_output_space(&Operation::Transcode(AudioFormat::Wav), "tests/test_data/estimated.wav")
+        .expect("Space check failed");
+}
+
+#[test]
+fn test_change_speed_outside_atempo_range() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let original = processor.duration().expect("Duration failed").as_secs_f64();
+    for factor in [0.25f32, 3.0, 4.0] {
+        let changed = processor.change_speed(factor).expect("Change speed failed");
+        let secs = changed.duration().expect("Duration failed").as_secs_f64();
+        let expected = original / f64::from(factor);
+        assert!((secs - expected).abs() < expected * 0.05 + 0.05, "{}x gave {}s, expected ~{}s", factor, secs, expected);
+        let _ = fs::remove_file(&changed.file_path);
+    }
+}
+
+#[test]
+fn test_pitch_shift_keeps_duration() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let original = processor.duration().expect("Duration failed").as_secs_f64();
+    for semitones in [7.0f32, -5.0] {
+        let shifted = processor.apply_effect(AudioEffect::PitchShift { semitones }).expect("Pitch shift failed");
+        let secs = shifted.duration().expect("Duration failed").as_secs_f64();
+        assert!((secs - original).abs() < 0.05, "{} semitones changed the length to {}s", semitones, secs);
+        assert_eq!(shifted.sample_rate().expect("Sample rate probe failed"), 44100);
+        let _ = fs::remove_file(&shifted.file_path);
+    }
+    assert!(matches!(
+        processor.apply_effect(AudioEffect::PitchShift { semitones: 30.0 }),
+        Err(AudioError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_from_lavfi() {
+    let processor = AudioProcessor::from_lavfi("sine=frequency=440:sample_rate=22050", Duration::from_secs(2))
+        .expect("Failed to create lavfi processor");
+    let duration = processor.duration().expect("Duration failed").as_secs_f64();
+    assert!((duration - 2.0).abs() < 0.05, "unexpected duration {}", duration);
+    let output_path = "tests/test_data/lavfi_sine.wav";
+    processor.transcode(AudioFormat::Wav, output_path).expect("Transcode failed");
+    let header = read_wav_header(output_path).expect("WAV header read failed");
+    assert_eq!(header.sample_rate, 22050);
+    let _ = fs::remove_file(output_path);
+
+    assert!(matches!(AudioProcessor::from_lavfi("sine", Duration::ZERO), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_apply_effect_mix() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let echo = || AudioEffect::Echo { delay: Duration::from_millis(250), decay: 0.5 };
+    let mixed = processor.apply_effect_mix(echo(), 0.5).expect("Effect mix failed");
+    assert!(Path::new(&mixed.file_path).exists());
+    assert!(mixed.duration().expect("Duration failed").as_secs_f64() >= 4.9);
+    let peak_db = mixed.level_stats().expect("Level stats failed").peak_db;
+    assert!(peak_db.is_finite() && peak_db < 0.0, "unexpected peak {}", peak_db);
+    for wet in [-0.1, 1.5, f32::NAN] {
+        assert!(matches!(processor.apply_effect_mix(echo(), wet), Err(AudioError::InvalidParameter(_))));
+    }
+    let _ = fs::remove_file(&mixed.file_path);
+
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let output = dir.path().join("parallel.wav");
+    let placed = processor.apply_effect_mix_to(echo(), 0.5, output.to_str().unwrap()).expect("Effect mix failed");
+    assert_eq!(placed.file_path, output.to_str().unwrap());
+    assert!(output.exists());
+}
+
+#[test]
+fn test_reverb() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let reverbed = processor
+        .apply_effect(AudioEffect::Reverb { room_size: 0.6, damping: 0.4, wet: 0.3 })
+        .expect("Reverb failed");
+    let size = fs::metadata(&reverbed.file_path).expect("Reverb output missing").len();
+    assert!(size > 0, "reverb output is empty");
+    assert!(matches!(
+        processor.apply_effect(AudioEffect::Reverb { room_size: 1.2, damping: 0.4, wet: 0.3 }),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let _ = fs::remove_file(&reverbed.file_path);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_probe_options_in_argv() {
+    // A stand-in ffmpeg that records its arguments and writes its output file.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let args_log = dir.path().join("args.txt");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "ffmpeg",
+        &format!(
+            "[ \"$1\" = -version ] && exit 0\necho \"$@\" > '{}'\nfor last; do :; done\necho rendered > \"$last\"\n",
+            args_log.display()
+        ),
+    );
+    let input = fake_input(dir.path(), "input.ts");
+
+    let config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg),
+        probesize: Some(50_000_000),
+        analyze_duration: Some(Duration::from_secs(20)),
+        ..AudioProcessorConfig::default()
+    };
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+    processor.reverse().expect("Reverse with fake ffmpeg failed");
+    let argv = fs::read_to_string(&args_log).expect("Fake ffmpeg did not record its arguments");
+    let input_at = argv.find(" -i ").expect("no input in argv");
+    let probesize_at = argv.find("-probesize 50000000").expect("probesize missing from argv");
+    let analyze_at = argv.find("-analyzeduration 20000000").expect("analyzeduration missing from argv");
+    assert!(probesize_at < input_at && analyze_at < input_at, "probe options must precede -i: {}", argv);
+}
+
+#[test]
+fn test_resample() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let resampled = processor.resample(22050).expect("Resample failed");
+    assert!(resampled.file_path.ends_with("resampled_22050_tone.wav"), "unexpected name {}", resampled.file_path);
+    assert_eq!(resampled.sample_rate().expect("Sample rate probe failed"), 22050);
+    let duration = resampled.duration().expect("Duration failed").as_secs_f64();
+    assert!((duration - 5.0).abs() < 0.05, "unexpected duration {}", duration);
+    for rate in [0, 4000, 1_000_000] {
+        assert!(matches!(processor.resample(rate), Err(AudioError::InvalidParameter(_))));
+    }
+    let _ = fs::remove_file(&resampled.file_path);
+}
+
+#[test]
+fn test_add_intro_outro() {
+    let episode = AudioProcessor::new(&setup_tone_file()).expect("Failed to create processor");
+    // A mono 22.05 kHz intro, unlike the stereo 44.1 kHz episode.
+    let intro_path = "tests/test_data/intro.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-f", "lavfi", "-i", "sine=frequency=880:sample_rate=22050", "-t", "2", "-ac", "1", intro_path, "-y"])
+        .status()
+        .expect("Failed to generate intro using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create intro");
+    let intro = AudioProcessor::new(intro_path).expect("Failed to create intro processor");
+    let outro = AudioProcessor::new(&setup_test_file()).expect("Failed to create outro processor");
+
+    let output_path = "tests/test_data/episode_full.wav";
+    let full = episode.add_intro_outro(Some(&intro), Some(&outro), output_path).expect("Intro/outro failed");
+    let expected: f64 = [&intro, &episode, &outro]
+        .iter()
+        .map(|part| part.duration().expect("Duration failed").as_secs_f64())
+        .sum();
+    let actual = full.duration().expect("Duration failed").as_secs_f64();
+    assert!((actual - expected).abs() < 0.1, "total {}s, expected {}s", actual, expected);
+    assert_eq!(full.channels().expect("Channel probe failed"), 2);
+    let _ = fs::remove_file(intro_path);
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_set_channels() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let mono = processor.set_channels(1).expect("Downmix failed");
+    let probe = std::process::Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "a:0", "-show_entries", "stream=channels", "-of", "csv=p=0", &mono.file_path])
+        .output()
+        .expect("Failed to run ffprobe");
+    assert_eq!(String::from_utf8_lossy(&probe.stdout).trim(), "1");
+
+    let stereo = mono.set_channels(2).expect("Upmix failed");
+    assert_eq!(stereo.channels().expect("Channel probe failed"), 2);
+    assert!(matches!(processor.set_channels(0), Err(AudioError::InvalidParameter(_))));
+    let _ = fs::remove_file(&mono.file_path);
+    let _ = fs::remove_file(&stereo.file_path);
+}
+
+#[test]
+fn test_is_true_stereo() {
+    // The tone fixture is a mono sine copied to both channels.
+    let dual_mono = AudioProcessor::new(&setup_tone_file()).expect("Failed to create processor");
+    assert!(!dual_mono.is_true_stereo().expect("Stereo check failed"));
+
+    let panned_path = "tests/test_data/panned.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-i", &dual_mono.file_path, "-af", "pan=stereo|c0=c0|c1=0.3*c1", panned_path, "-y"])
+        .status()
+        .expect("Failed to generate panned file using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create panned file");
+    let panned = AudioProcessor::new(panned_path).expect("Failed to create processor");
+    assert!(panned.is_true_stereo().expect("Stereo check failed"));
+    let _ = fs::remove_file(panned_path);
+}
+
+#[test]
+fn test_transcode_with() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output_path = "tests/test_data/transcode_with.mp3";
+    let options = TranscodeOptions::new().codec("libmp3lame").bitrate(BitrateMode::Constant(128));
+    processor.transcode_with(output_path, options).expect("transcode_with failed");
+    assert!(fs::metadata(output_path).expect("MP3 output missing").len() > 0);
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_apply_operation() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let direct_path = "tests/test_data/direct_volume.wav";
+    let direct = processor.adjust_volume_to(1.5, direct_path).expect("Volume adjustment failed");
+    let applied = processor.apply(Operation::Volume(1.5)).expect("Applying the operation failed");
+    assert!(applied.is_bit_identical(&direct).expect("Comparison failed"));
+    let _ = fs::remove_file(direct_path);
+    let _ = fs::remove_file(&applied.file_path);
+}
+
+#[test]
+fn test_trim_silence() {
+    let tone = setup_tone_file();
+    let padded_path = "tests/test_data/padded_tone.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-i", &tone, "-af", "adelay=delays=2000:all=1,apad=pad_dur=2", padded_path, "-y"])
+        .status()
+        .expect("Failed to pad tone using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to pad tone");
+    let padded = AudioProcessor::new(padded_path).expect("Failed to create processor");
+    let before = padded.duration().expect("Duration failed").as_secs_f64();
+
+    let trimmed = padded.trim_silence(-50.0, Duration::from_millis(500)).expect("Silence trim failed");
+    let after = trimmed.duration().expect("Duration failed").as_secs_f64();
+    assert!(after < before - 3.5, "duration only went from {}s to {}s", before, after);
+    assert!((after - 5.0).abs() < 0.1, "expected about 5s of tone, got {}s", after);
+    assert!(matches!(padded.trim_silence(3.0, Duration::from_millis(500)), Err(AudioError::InvalidParameter(_))));
+    let _ = fs::remove_file(padded_path);
+    let _ = fs::remove_file(&trimmed.file_path);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_missing_encoder() {
+    // A stand-in ffmpeg built without libmp3lame.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\n\
+         printf 'Encoders:\\n ------\\n A....D flac  FLAC\\n A....D pcm_s16le  PCM\\n'\n",
+    );
+    let input = fake_input(dir.path(), "input.wav");
+
+    let processor = AudioProcessor::with_ffmpeg_path(input.to_str().unwrap(), fake_ffmpeg.to_str().unwrap())
+        .expect("Failed to create processor");
+    assert!(processor.has_encoder("flac").expect("Encoder query failed"));
+    assert!(!processor.has_encoder("libmp3lame").expect("Encoder query failed"));
+    let output = dir.path().join("output.mp3");
+    match processor.transcode(AudioFormat::Mp3, output.to_str().unwrap()) {
+        Err(AudioError::UnsupportedFormat(message)) => assert!(message.contains("libmp3lame"), "{}", message),
+        other => panic!("expected UnsupportedFormat, got {:?}", other),
+    }
+    assert!(matches!(
+        processor.transcode_to_max_size(AudioFormat::Mp3, 50_000, output.to_str().unwrap()),
+        Err(AudioError::UnsupportedFormat(_))
+    ));
+    let flac_output = dir.path().join("output.flac").to_string_lossy().into_owned();
+    assert!(matches!(
+        processor.tee(&[(AudioFormat::Flac, flac_output.clone()), (AudioFormat::Mp3, output.to_string_lossy().into_owned())]),
+        Err(AudioError::UnsupportedFormat(_))
+    ));
+    let mut progress = processor.transcode_progress_iter(AudioFormat::Mp3, output.to_str().unwrap());
+    assert!(matches!(progress.next(), Some(Err(AudioError::UnsupportedFormat(_)))));
+    assert!(progress.next().is_none());
+    assert!(matches!(
+        processor.transcode_with_progress(output.to_str().unwrap(), |_| panic!("transcode started")),
+        Err(AudioError::UnsupportedFormat(_))
+    ));
+    assert!(matches!(
+        processor.transcode_with_progress(dir.path().join("output").to_str().unwrap(), |_| panic!("transcode started")),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    assert!(!output.exists());
+    assert!(!Path::new(&flac_output).exists());
+}
+
+#[test]
+fn test_detect_silence_gap() {
+    fs::create_dir_all("tests/test_data").expect("Failed to create test_data directory");
+    let gap_path = "tests/test_data/gap.wav";
+    // 5 s of tone, muted from 1.5 s to 3.5 s, followed by 1 s of trailing silence.
+    let status = std::process::Command::new("ffmpeg")
+        .args([
+            "-f", "lavfi", "-i", "sine=frequency=440:duration=5",
+            "-af", "volume=enable='between(t,1.5,3.5)':volume=0,apad=pad_dur=1",
+            gap_path, "-y"
+        ])
+        .status()
+        .expect("Failed to generate gap file using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create gap file");
+
+    let processor = AudioProcessor::new(gap_path).expect("Failed to create processor");
+    let regions = processor.detect_silence(-50.0, Duration::from_millis(500)).expect("Silence detection failed");
+    assert_eq!(regions.len(), 2, "unexpected regions {:?}", regions);
+    let (start, end) = regions[0];
+    assert!((start.as_secs_f64() - 1.5).abs() < 0.05, "gap starts at {:?}", start);
+    assert!((end.as_secs_f64() - 3.5).abs() < 0.05, "gap ends at {:?}", end);
+    // The trailing silence has no silence_end line and is closed at the end of the file.
+    let (start, end) = regions[1];
+    assert!((start.as_secs_f64() - 5.0).abs() < 0.05, "trailing silence starts at {:?}", start);
+    assert!((end.as_secs_f64() - 6.0).abs() < 0.05, "trailing silence ends at {:?}", end);
+    let _ = fs::remove_file(gap_path);
+}
+
+#[test]
+fn test_merge_audios_with_offsets() {
+    let file = setup_tone_file();
+    let first = AudioProcessor::new(&file).expect("Failed to create processor");
+    let second = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output_path = "tests/test_data/merged_with_offsets.wav";
+    let (merged, offsets) = AudioProcessor::merge_audios_with_offsets(&[first, second], output_path).expect("Merge failed");
+    assert_eq!(offsets.len(), 2);
+    assert_eq!(offsets[0], Duration::ZERO);
+    assert!((offsets[1].as_secs_f64() - 5.0).abs() < 0.01, "second input starts at {:?}", offsets[1]);
+    let total = merged.duration().expect("Duration failed").as_secs_f64();
+    assert!((total - 10.0).abs() < 0.05, "merged duration {}", total);
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_set_tags_round_trip() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let mp3_path = "tests/test_data/tone_untitled.mp3";
+    processor.transcode(AudioFormat::Mp3, mp3_path).expect("Transcode failed");
+    let mp3 = AudioProcessor::new(mp3_path).expect("Failed to create processor");
+
+    let mut tags = std::collections::HashMap::new();
+    tags.insert("title".to_string(), "Don't Stop: \"Live\" = 100% ; $HOME & more".to_string());
+    tags.insert("artist".to_string(), "Sigur Rós".to_string());
+    let tagged = mp3.set_tags(&tags, "tests/test_data/tone_titled.mp3").expect("Setting tags failed");
+
+    let read = read_tags(&tagged.file_path).expect("Reading tags failed");
+    assert_eq!(read.get("title"), tags.get("title"));
+    assert_eq!(read.get("artist"), tags.get("artist"));
+    let _ = fs::remove_file(mp3_path);
+    let _ = fs::remove_file(&tagged.file_path);
+}
+
+#[test]
+fn test_split_and_process() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let output_dir = "tests/test_data/split_and_process";
+    let _ = fs::remove_dir_all(output_dir);
+    let processed = processor
+        .split_and_process(Duration::from_secs(2), Operation::Normalize, 2, output_dir)
+        .expect("Split and process failed");
+    // 5 seconds in 2-second segments: 2s, 2s and the 1s remainder.
+    assert_eq!(processed.len(), 3);
+    for segment in &processed {
+        assert!(Path::new(&segment.file_path).exists(), "missing {}", segment.file_path);
+        assert!(segment.file_path.starts_with(output_dir));
+    }
+    assert!(matches!(
+        processor.split_and_process(Duration::from_secs(2), Operation::Normalize, 0, output_dir),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let _ = fs::remove_dir_all(output_dir);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_transcode_async_concurrently() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let outputs: Vec<String> = (0..10).map(|index| format!("tests/test_data/async_{}.mp3", index)).collect();
+    let results = futures::future::join_all(
+        outputs.iter().map(|output| processor.transcode_async(AudioFormat::Mp3, output)),
+    )
+    .await;
+    for (result, output) in results.into_iter().zip(&outputs) {
+        result.expect("Async transcode failed");
+        assert!(Path::new(output).exists(), "missing {}", output);
+        let _ = fs::remove_file(output);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_temp_dir() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    // TMPDIR is process-wide, so the merge runs in a child test process that has it
+    // pointed at a non-UTF-8 directory; this process only prepares and checks the run.
+    if let Some(input) = std::env::var_os("NON_UTF8_TMPDIR_INPUT") {
+        let fake_ffmpeg = std::env::var("NON_UTF8_TMPDIR_FFMPEG").expect("fake ffmpeg path not set");
+        let input = input.to_str().expect("input path is UTF-8");
+        let processor = AudioProcessor::with_ffmpeg_path(input, &fake_ffmpeg).expect("Failed to create processor");
+        let output = Path::new(input).with_file_name("merged.wav");
+        match AudioProcessor::merge_audios(&[processor], output.to_str().unwrap()) {
+            Err(AudioError::InvalidParameter(message)) => assert!(message.contains("UTF-8"), "{}", message),
+            other => panic!("expected InvalidParameter, got {:?}", other),
+        }
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(dir.path(), "ffmpeg", "exit 0\n");
+    let input = fake_input(dir.path(), "input.wav");
+    let temp_dir = dir.path().join(OsStr::from_bytes(b"tmp-\xff"));
+    fs::create_dir(&temp_dir).expect("Failed to create non-UTF-8 directory");
+
+    let status = std::process::Command::new(std::env::current_exe().expect("no test binary path"))
+        .args(["test_non_utf8_temp_dir", "--exact", "--nocapture"])
+        .env("TMPDIR", &temp_dir)
+        .env("NON_UTF8_TMPDIR_INPUT", &input)
+        .env("NON_UTF8_TMPDIR_FFMPEG", &fake_ffmpeg)
+        .status()
+        .expect("Failed to rerun test binary");
+    assert!(status.success(), "merge under a non-UTF-8 TMPDIR did not fail cleanly");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_transcode_with_progress() {
+    // A stand-in ffmpeg that reports progress through a 5-second input, out of order once.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\n\
+         [ \"$2\" = -encoders ] && printf ' ------\\n A....D libmp3lame  MP3\\n' && exit 0\n\
+         printf 'out_time_ms=1000000\\nprogress=continue\\nout_time_ms=2500000\\nout_time_ms=2000000\\n'\n\
+         printf 'out_time_ms=4000000\\nprogress=end\\n'\n",
+    );
+    let fake_ffprobe = fake_tool(dir.path(), "ffprobe", "echo 5.000000\n");
+    let input = fake_input(dir.path(), "input.wav");
+
+    let config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg),
+        ffprobe_path: Some(fake_ffprobe),
+        ..AudioProcessorConfig::default()
+    };
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+    let mut progress = Vec::new();
+    let output = dir.path().join("output.mp3");
+    processor
+        .transcode_with_progress(output.to_str().unwrap(), |fraction| progress.push(fraction))
+        .expect("Transcode failed");
+    assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]), "progress decreased: {:?}", progress);
+    assert_eq!(progress, vec![0.2, 0.5, 0.8, 1.0]);
+}
+
+#[test]
+fn test_convolution() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let ir_path = "tests/test_data/impulse_response.wav";
+    // A 100 ms burst of decaying noise stands in for a recorded room.
+    let status = std::process::Command::new("ffmpeg")
+        .args([
+            "-f", "lavfi", "-i", "anoisesrc=d=0.1:a=0.5",
+            "-af", "afade=t=out:st=0:d=0.1",
+            ir_path, "-y"
+        ])
+        .status()
+        .expect("Failed to generate impulse response using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create impulse response");
+
+    let convolved = processor
+        .apply_effect(AudioEffect::Convolution { impulse_response_path: ir_path.to_string() })
+        .expect("Convolution failed");
+    assert!(Path::new(&convolved.file_path).exists());
+    let duration = convolved.duration().expect("Duration probe failed").as_secs_f64();
+    assert!((duration - 5.0).abs() < 0.2, "expected about 5s of output, got {}s", duration);
+
+    let missing = AudioEffect::Convolution { impulse_response_path: "tests/test_data/missing_ir.wav".to_string() };
+    assert!(matches!(processor.apply_effect(missing), Err(AudioError::InvalidParameter(_))));
+    let _ = fs::remove_file(ir_path);
+    let _ = fs::remove_file(&convolved.file_path);
+}
+
+#[test]
+fn test_waveform_peaks() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let peaks = processor.waveform_peaks(1000).expect("Peak extraction failed");
+    // 5 s at 44.1 kHz is 220500 samples: 220 full buckets and a partial one, each a min/max pair.
+    assert_eq!(peaks.len(), 221 * 2);
+    for pair in peaks.chunks_exact(2) {
+        assert!(-1.0 <= pair[0] && pair[0] <= pair[1] && pair[1] <= 1.0, "bad peak pair {:?}", pair);
+    }
+    // Every 1000-sample bucket spans two cycles of the tone, so it reaches both extremes.
+    assert!(peaks.chunks_exact(2).take(220).all(|pair| pair[0] < -0.1 && pair[1] > 0.1));
+    assert!(matches!(processor.waveform_peaks(0), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_normalize_keeps_channel_count() {
+    fs::create_dir_all("tests/test_data").expect("Failed to create test_data directory");
+    let mono_path = "tests/test_data/normalize_mono.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=3", "-ac", "1", mono_path, "-y"])
+        .status()
+        .expect("Failed to generate mono file using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create mono file");
+    let processor = AudioProcessor::new(mono_path).expect("Failed to create processor");
+
+    let normalized = processor.normalize().expect("Normalization failed");
+    assert_eq!(normalized.channels().expect("Channel probe failed"), 1);
+
+    // Dual mono: the same tone on both channels, measured as the mono signal it is.
+    let stereo = processor.set_channels(2).expect("Upmix failed");
+    let dual_mono = stereo.normalize_loudness(-23.0, true).expect("Dual-mono normalization failed");
+    assert_eq!(dual_mono.channels().expect("Channel probe failed"), 2);
+    for path in [mono_path, normalized.file_path.as_str(), stereo.file_path.as_str(), dual_mono.file_path.as_str()] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[test]
+fn test_transcode_checks_extension() {
+    assert_eq!(AudioFormat::Mp3.extension(), "mp3");
+    assert_eq!(AudioFormat::Ogg.default_codec(), "libvorbis");
+
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    match processor.transcode(AudioFormat::Mp3, "tests/test_data/mismatched.wav") {
+        Err(AudioError::InvalidParameter(message)) => assert!(message.contains(".mp3"), "{}", message),
+        other => panic!("expected InvalidParameter, got {:?}", other),
+    }
+    assert!(!Path::new("tests/test_data/mismatched.wav").exists());
+
+    // A missing extension is filled in; a differently cased one is accepted as is.
+    processor.transcode(AudioFormat::Flac, "tests/test_data/no_extension").expect("Transcode failed");
+    assert!(Path::new("tests/test_data/no_extension.flac").exists());
+    processor.transcode(AudioFormat::Wav, "tests/test_data/upper_case.WAV").expect("Transcode failed");
+    assert!(Path::new("tests/test_data/upper_case.WAV").exists());
+    let _ = fs::remove_file("tests/test_data/no_extension.flac");
+    let _ = fs::remove_file("tests/test_data/upper_case.WAV");
+}
+
+#[test]
+fn test_change_speed_keeps_sample_rate() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let input_rate = processor.sample_rate().expect("Sample rate probe failed");
+    let faster = processor.change_speed(1.5).expect("Speed change failed");
+    assert_eq!(faster.sample_rate().expect("Sample rate probe failed"), input_rate);
+
+    let output = "tests/test_data/tone_faster_22k.wav";
+    let resampled = processor.change_speed_at_rate_to(1.5, 22050, output).expect("Speed change failed");
+    assert_eq!(resampled.sample_rate().expect("Sample rate probe failed"), 22050);
+    assert!(matches!(processor.change_speed_at_rate(1.5, 100), Err(AudioError::InvalidParameter(_))));
+    let _ = fs::remove_file(&faster.file_path);
+    let _ = fs::remove_file(output);
+}
+
+#[test]
+fn test_volume_and_speed_factor_validation() {
+    // A stdin processor needs no ffmpeg to construct, and invalid factors are rejected
+    // before anything is spawned.
+    let processor = AudioProcessor::from_stdin(AudioFormat::Wav, std::io::empty());
+    for factor in [-1.0, -0.01, f32::NAN, f32::INFINITY] {
+        match processor.adjust_volume(factor) {
+            Err(AudioError::InvalidParameter(message)) => assert!(message.contains("volume factor"), "{}", message),
+            other => panic!("expected InvalidParameter for volume {}, got {:?}", factor, other),
+        }
+    }
+    for factor in [0.0, -1.0, f32::NAN] {
+        match processor.change_speed(factor) {
+            Err(AudioError::InvalidParameter(message)) => assert!(message.contains("speed factor"), "{}", message),
+            other => panic!("expected InvalidParameter for speed {}, got {:?}", factor, other),
+        }
+    }
+    assert!(set_volume("in.wav", "out.wav", -2.0).is_err_and(|e| matches!(e, AudioError::InvalidParameter(_))));
+    // Valid factors pass validation and fail only on the missing ffmpeg or empty input.
+    assert!(!matches!(processor.adjust_volume(0.5), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_info() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let info = processor.info().expect("Info probe failed");
+    assert_eq!(info.sample_rate, 44100);
+    assert_eq!(info.channels, 2);
+    assert_eq!(info.codec, "pcm_s16le");
+    assert_eq!(info.bitrate, Some(44100 * 2 * 16));
+    assert!((info.duration.as_secs_f64() - 5.0).abs() < 0.05, "unexpected duration {:?}", info.duration);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_info_tolerates_missing_fields() {
+    // An ffprobe reporting a stream with no bitrate anywhere, as for some WAVs.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(dir.path(), "ffmpeg", "exit 0\n");
+    let fake_ffprobe = fake_tool(
+        dir.path(),
+        "ffprobe",
+        "cat <<'EOF'\n{\"streams\": [{\"codec_name\": \"pcm_s24le\", \"sample_rate\": \"48000\", \
+         \"channels\": 1, \"duration\": \"2.500000\"}], \"format\": {}}\nEOF\n",
+    );
+    let input = fake_input(dir.path(), "input.wav");
+
+    let config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg),
+        ffprobe_path: Some(fake_ffprobe),
+        ..AudioProcessorConfig::default()
+    };
+    let processor = AudioProcessor::with_config(input.to_str().unwrap(), config).expect("Failed to create processor");
+    let expected = FileInfo {
+        duration: Duration::from_millis(2500),
+        sample_rate: 48000,
+        channels: 1,
+        codec: "pcm_s24le".to_string(),
+        bitrate: None,
+    };
+    assert_eq!(processor.info().expect("Info probe failed"), expected);
+}
+
+#[test]
+fn test_generate_noise() {
+    fs::create_dir_all("tests/test_data").expect("Failed to create test_data directory");
+    let output = "tests/test_data/pink_noise.wav";
+    let noise = generate_noise(NoiseColor::Pink, Duration::from_secs(2), 44100, output).expect("Noise generation failed");
+    let duration = noise.duration().expect("Duration probe failed").as_secs_f64();
+    assert!((duration - 2.0).abs() < 0.05, "expected 2s of noise, got {}s", duration);
+    assert!(noise.level_stats().expect("Level analysis failed").rms_db > -40.0, "noise is silent");
+    assert!(matches!(
+        generate_noise(NoiseColor::White, Duration::ZERO, 44100, output),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let _ = fs::remove_file(output);
+}
+
+#[test]
+fn test_trim_validates_range() {
+    let file = setup_test_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    // The file is 5 seconds long.
+    assert!(matches!(
+        processor.trim(Duration::from_secs(6), Duration::from_secs(8)),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        processor.trim(Duration::from_secs(5), Duration::from_secs(7)),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        processor.trim(Duration::from_secs(3), Duration::from_secs(3)),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        processor.trim(Duration::from_secs(3), Duration::from_secs(2)),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let clamped = processor.trim(Duration::from_secs(3), Duration::from_secs(10)).expect("Clamped trim failed");
+    let duration = clamped.duration().expect("Duration probe failed").as_secs_f64();
+    assert!((duration - 2.0).abs() < 0.05, "expected 2s after clamping, got {}s", duration);
+    let _ = fs::remove_file(&clamped.file_path);
+}
+
+#[test]
+fn test_transcode_bytes_in_memory() {
+    let file = setup_tone_file();
+    let data = fs::read(&file).expect("Failed to read tone file");
+    let processor = AudioProcessor::from_bytes(data, AudioFormat::Wav);
+    let mp3 = processor.transcode_to_bytes(AudioFormat::Mp3).expect("In-memory transcode failed");
+    // MP3 streams start with an ID3 tag or a frame sync.
+    assert!(mp3.starts_with(b"ID3") || (mp3.len() > 1 && mp3[0] == 0xFF && mp3[1] & 0xE0 == 0xE0));
+
+    // Decoding the bytes back gives the tone's length.
+    let output_path = "tests/test_data/from_bytes.mp3";
+    fs::write(output_path, &mp3).expect("Failed to write mp3 bytes");
+    let decoded = AudioProcessor::new(output_path).expect("Failed to create processor");
+    let duration = decoded.duration().expect("Duration probe failed").as_secs_f64();
+    assert!((duration - 5.0).abs() < 0.2, "expected about 5s, got {}s", duration);
+    let _ = fs::remove_file(output_path);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_transcode_to_bytes_streams_both_ways() {
+    // A stand-in ffmpeg that copies stdin to stdout, so the bytes must survive the round trip.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let fake_ffmpeg = fake_tool(
+        dir.path(),
+        "ffmpeg",
+        "[ \"$1\" = -version ] && exit 0\n\
+         [ \"$2\" = -encoders ] && printf ' ------\\n A....D libmp3lame  MP3\\n' && exit 0\n\
+         exec cat\n",
+    );
+
+    // Larger than a pipe buffer, so writing and reading must overlap.
+    let data: Vec<u8> = (0..1_000_000u32).map(|i| (i % 251) as u8).collect();
+    let mut processor = AudioProcessor::from_bytes(data.clone(), AudioFormat::Wav);
+    processor.config.ffmpeg_path = Some(fake_ffmpeg);
+    assert_eq!(processor.transcode_to_bytes(AudioFormat::Mp3).expect("In-memory transcode failed"), data);
+    // The bytes were consumed by the first run.
+    assert!(matches!(processor.transcode_to_bytes(AudioFormat::Mp3), Err(AudioError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_read_samples_range() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let (samples, sample_rate) = processor
+        .read_samples_range(Duration::from_secs(1), Duration::from_secs(2))
+        .expect("Reading the window failed");
+    assert_eq!(sample_rate, processor.sample_rate().expect("Sample rate probe failed"));
+    let expected = sample_rate as usize;
+    // Seeking lands on a frame boundary, so allow a few milliseconds either way.
+    assert!(samples.len().abs_diff(expected) <= expected / 100, "got {} samples, expected {}", samples.len(), expected);
+    assert!(samples.iter().any(|sample| sample.abs() > 0.1), "the tone window is silent");
+    assert!(matches!(
+        processor.read_samples_range(Duration::from_secs(2), Duration::from_secs(1)),
+        Err(AudioError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_merge_quoted_file_name() {
+    let file = setup_tone_file();
+    let quoted_path = "tests/test_data/it's a tone.wav";
+    fs::copy(&file, quoted_path).expect("Failed to copy tone");
+    let quoted = AudioProcessor::new(quoted_path).expect("Failed to create processor");
+    let tone = AudioProcessor::new(&file).expect("Failed to create processor");
+    let merged_output = "tests/test_data/merged_quoted.wav";
+    let merged = AudioProcessor::merge_audios(&[quoted, tone], merged_output).expect("Merge audios failed");
+    let duration = merged.duration().expect("Duration probe failed");
+    assert!((duration.as_secs_f64() - 10.0).abs() < 0.1, "merged duration is {:?}", duration);
+    let _ = fs::remove_file(quoted_path);
+    let _ = fs::remove_file(merged_output);
+}
+
+#[test]
+fn test_fix_phase() {
+    let tone_file = setup_tone_file();
+    let inverted_path = "tests/test_data/tone_inverted.wav";
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-i", &tone_file, "-af", "pan=stereo|c0=c0|c1=-1*c1", inverted_path, "-y"])
+        .status()
+        .expect("Failed to invert channel using ffmpeg");
+    assert!(status.success(), "ffmpeg failed to create inverted tone");
+
+    let inverted = AudioProcessor::new(inverted_path).expect("Failed to create processor");
+    assert!(inverted.phase_correlation().expect("Correlation failed") < -0.9);
+    let (fixed, applied) = inverted.fix_phase().expect("Fix phase failed");
+    assert!(applied);
+    assert!(fixed.phase_correlation().expect("Correlation failed") > 0.9);
+
+    let (unchanged, applied) = fixed.fix_phase().expect("Fix phase failed");
+    assert!(!applied);
+    assert_eq!(unchanged.file_path, fixed.file_path);
+    let _ = fs::remove_file(inverted_path);
+    let _ = fs::remove_file(&fixed.file_path);
+}
+
+#[test]
+fn test_merge_with_crossfade() {
+    let file = setup_tone_file();
+    let first = AudioProcessor::new(&file).expect("Failed to create processor");
+    let second = AudioProcessor::new(&file).expect("Failed to create processor");
+    let clip = first.duration().expect("Duration probe failed");
+    let output = "tests/test_data/crossfaded.wav";
+    let merged = AudioProcessor::merge_with_crossfade(&[first.clone(), second.clone()], Duration::from_secs(1), output)
+        .expect("Crossfade merge failed");
+    let duration = merged.duration().expect("Duration probe failed");
+    assert!(duration < clip * 2, "crossfaded {:?} is not shorter than {:?}", duration, clip * 2);
+    assert!((duration.as_secs_f64() - (clip * 2 - Duration::from_secs(1)).as_secs_f64()).abs() < 0.1);
+    assert!(matches!(
+        AudioProcessor::merge_with_crossfade(&[first, second], clip, output),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let _ = fs::remove_file(output);
+}
+
+#[test]
+fn test_prepend_silence() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let padded = processor.prepend_silence(Duration::from_secs(2)).expect("Prepend silence failed");
+    let duration = padded.duration().expect("Duration probe failed");
+    assert!((duration.as_secs_f64() - 7.0).abs() < 0.1, "padded duration is {:?}", duration);
+    let (lead_in, _) = padded
+        .read_samples_range(Duration::ZERO, Duration::from_millis(1900))
+        .expect("Reading the lead-in failed");
+    assert!(lead_in.iter().all(|sample| *sample == 0.0), "the lead-in is not silent");
+    let (tone, _) = padded
+        .read_samples_range(Duration::from_secs(3), Duration::from_secs(4))
+        .expect("Reading the tone failed");
+    assert!(tone.iter().any(|sample| sample.abs() > 0.1), "the tone is missing after the lead-in");
+    let _ = fs::remove_file(&padded.file_path);
+}
+
+#[test]
+fn test_transcode_alac_to_m4a() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    assert!(matches!(
+        processor.transcode_with("tests/test_data/flac_in.mp3", TranscodeOptions::new().codec(Codec::Flac)),
+        Err(AudioError::InvalidParameter(_))
+    ));
+
+    let output_path = "tests/test_data/alac.m4a";
+    processor
+        .transcode_with(output_path, TranscodeOptions::new().codec(Codec::Alac))
+        .expect("ALAC transcode failed");
+    let info = AudioProcessor::new(output_path).expect("Failed to open output").info().expect("Info probe failed");
+    assert_eq!(info.codec, Codec::Alac.as_ffmpeg_arg());
+    let _ = fs::remove_file(output_path);
+}
+
+#[test]
+fn test_adjust_volume_db() {
+    let mean_volume = |path: &str| -> f32 {
+        let output = std::process::Command::new("ffmpeg")
+            .args(["-i", path, "-af", "volumedetect", "-f", "null", "-"])
+            .output()
+            .expect("Failed to run volumedetect");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let (_, rest) = stderr.split_once("mean_volume:").expect("volumedetect printed no mean volume");
+        rest.split_whitespace().next().unwrap().parse().expect("Failed to parse mean volume")
+    };
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let boosted = processor.adjust_volume_db(6.0).expect("Adjust volume failed");
+    let gain = mean_volume(&boosted.file_path) - mean_volume(&file);
+    assert!((gain - 6.0).abs() < 0.5, "measured gain was {} dB", gain);
+    assert!(matches!(processor.adjust_volume_db(200.0), Err(AudioError::InvalidParameter(_))));
+    assert!(matches!(processor.adjust_volume_db(f32::NAN), Err(AudioError::InvalidParameter(_))));
+    let _ = fs::remove_file(&boosted.file_path);
+}
+
+#[test]
+fn test_apply_effect_ranged() {
+    let file = setup_tone_file();
+    let processor = AudioProcessor::new(&file).expect("Failed to create processor");
+    let echo = AudioEffect::Echo { delay: Duration::from_millis(100), decay: 0.5 };
+    let ranged = processor
+        .apply_effect_ranged(echo.clone(), Duration::from_secs(2), Duration::from_secs(4))
+        .expect("Ranged effect failed");
+    assert!((ranged.duration().unwrap().as_secs_f64() - 5.0).abs() < 0.1);
+
+    let max_difference = |start: u64, end: u64| -> f32 {
+        let window = (Duration::from_millis(start), Duration::from_millis(end));
+        let (original, _) = processor.read_samples_range(window.0, window.1).expect("Reading the input failed");
+        let (effected, _) = ranged.read_samples_range(window.0, window.1).expect("Reading the output failed");
+        original.iter().zip(&effected).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+    };
+    assert!(max_difference(500, 1500) < 0.001, "audio before the range was changed");
+    assert!(max_difference(2500, 3500) > 0.01, "the echo was not applied inside the range");
+    assert!(max_difference(4200, 4800) < 0.001, "audio after the range was changed");
+
+    assert!(matches!(
+        processor.apply_effect_ranged(echo, Duration::from_secs(3), Duration::from_secs(2)),
+        Err(AudioError::InvalidParameter(_))
+    ));
+    let _ = fs::remove_file(&ranged.file_path);
+}
+
+#[test]
+fn test_merge_duration_matches_inputs() {
+    let silence = AudioProcessor::new(&setup_test_file()).expect("Failed to create processor");
+    let tone = AudioProcessor::new(&setup_tone_file()).expect("Failed to create processor");
+    let expected = silence.duration().unwrap() + tone.duration().unwrap();
+    let merged_output = "tests/test_data/merged_verified.wav";
+    let merged = AudioProcessor::merge_audios(&[silence, tone], merged_output).expect("Merge audios failed");
+    let duration = merged.duration().expect("Duration probe failed");
+    assert!(duration.abs_diff(expected) < Duration::from_millis(100), "merged {:?}, expected {:?}", duration, expected);
+    let _ = fs::remove_file(merged_output);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_merge_reencodes_when_duration_is_off() {
+    // Inputs probe as 5s each, but the stream-copied merge as 3s, as if timestamps overlapped.
+    let dir = tempfile::tempdir().expect("Failed to create temp directory");
+    let log = dir.path().join("ffmpeg.log");
+    let script = format!("[ \"$1\" = -version ] && exit 0\necho \"$*\" >> '{}'\n", log.display());
+    let fake_ffmpeg = fake_tool(dir.path(), "ffmpeg", &script);
+    let fake_ffprobe = fake_tool(
+        dir.path(),
+        "ffprobe",
+        "case \"$*\" in *channels*) echo 2 ;; *sample_rate*) echo 44100 ;; *merged.wav) echo 3.0 ;; *) echo 5.0 ;; esac\n",
+    );
+    let config = AudioProcessorConfig {
+        ffmpeg_path: Some(fake_ffmpeg),
+        ffprobe_path: Some(fake_ffprobe),
+        ..AudioProcessorConfig::default()
+    };
+    let inputs: Vec<AudioProcessor> = ["a.wav", "b.wav"]
+        .iter()
+        .map(|name| {
+            let path = fake_input(dir.path(), name);
+            AudioProcessor::with_config(path.to_str().unwrap(), config.clone()).expect("Failed to create processor")
+        })
+        .collect();
+    let output = dir.path().join("merged.wav");
+    AudioProcessor::merge_audios(&inputs, output.to_str().unwrap()).expect("Merge audios failed");
+
+    let runs = fs::read_to_string(&log).expect("Failed to read ffmpeg log");
+    let runs: Vec<&str> = runs.lines().collect();
+    assert_eq!(runs.len(), 2, "expected a copy merge and a re-encode, got {:?}", runs);
+    assert!(runs[0].contains("-f concat") && runs[0].contains("-c copy"));
+    assert!(runs[1].contains("[1:a]aresample=44100[a1]") && runs[1].contains("concat=n=2:v=0:a=1"));
+}